@@ -0,0 +1,93 @@
+//! Lexer throughput on multi-megabyte inputs, made by repeating a small program.
+//!
+//! Run with `cargo bench --bench lexer`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use niff::diagnostics::Diagnostics;
+use niff::lexer::{lexer, Lexer};
+use niff::span::FileId;
+
+/// About how big every input is.
+const SIZE: usize = 4 * 1024 * 1024;
+
+/// Only ASCII, a bit of everything the lexer knows about.
+const PROGRAM: &str = r#"// Adds numbers together.
+fn add(a i32, b i32) i32 {
+    return a + b;
+}
+
+/* The
+   entry point */
+fn main() {
+    var mut total: i32 = 0;
+    const limit = 0xFF_u32;
+
+    for i in 0..=limit {
+        if (i % 2 == 0 && i != 10) {
+            total += add(i, 1_000);
+        } elif (i >= 100) {
+            break;
+        } else {
+            total <<= 1;
+        }
+    }
+
+    println("total: \t{}\n", total, 1.5e-3, 'x');
+}
+"#;
+
+/// Comments, strings, characters and identifiers that aren't ASCII.
+const UNICODE: &str = r#"// Grüße aus Köln, こんにちは 🌍
+fn main() {
+    var greeting = "héllo wörld, 日本語のテキスト";
+    var emoji = '🎉';
+    var größe = 10;
+    var 名前 = größe;
+    /* Ελληνικά και русский текст */
+    println(greeting, emoji);
+}
+"#;
+
+/// Long string literals, most of them can be borrowed from the source.
+const STRINGS: &str = r#"var a = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod";
+var b = r"tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam";
+var c = "quis nostrud\texercitation ullamco laboris nisi ut aliquip ex ea commodo\n";
+"#;
+
+fn repeat(src: &str) -> String {
+    src.repeat(SIZE / src.len() + 1)
+}
+
+fn bench_lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+
+    for (name, src) in [
+        ("ascii", PROGRAM),
+        ("unicode", UNICODE),
+        ("strings", STRINGS),
+    ] {
+        let src = repeat(src);
+
+        group.throughput(Throughput::Bytes(src.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| lexer(black_box(&src), FileId(0), &mut Diagnostics::new()))
+        });
+    }
+
+    // Without keeping the tokens around, like the parser does.
+    let src = repeat(PROGRAM);
+
+    group.throughput(Throughput::Bytes(src.len() as u64));
+    group.bench_function("streaming", |b| {
+        b.iter(|| Lexer::new(black_box(&src), FileId(0)).count())
+    });
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_lexer
+}
+criterion_main!(benches);
@@ -0,0 +1,251 @@
+//! Errors and warnings reported to the user.
+//!
+//! Nothing in the lexer or the parser should panic because of bad input. Instead a
+//! [`Diagnostic`] is created and pushed into [`Diagnostics`], so many of them can be
+//! reported at once.
+
+pub mod render;
+
+use std::fmt;
+
+use crate::span::Span;
+
+/// How bad is it?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Every kind of error has its own code, so they are easy to search for.
+///
+/// E00xx are from the lexer, E01xx from the parser and E02xx from expressions. Warnings are
+/// numbered the same way, but start with a W.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnknownCharacter,
+    UnterminatedChar,
+    UnterminatedBlockComment,
+    IntegerOverflow,
+    InvalidEscape,
+    UnterminatedString,
+    InvalidNumber,
+    UnexpectedToken,
+    OutsideOfLoop,
+    MisplacedInnerDoc,
+    InvalidExpressionToken,
+    UnbalancedParens,
+    MissingOperand,
+    UnsupportedOperation,
+    DivisionByZero,
+    ConstantOverflow,
+    NotAssignable,
+    MismatchedTypes,
+    NegativeShift,
+    ConfusableIdentifier,
+    MixedScriptIdentifier,
+    UnusedDocComment,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnknownCharacter => "E0001",
+            ErrorCode::UnterminatedChar => "E0002",
+            ErrorCode::UnterminatedBlockComment => "E0003",
+            ErrorCode::IntegerOverflow => "E0004",
+            ErrorCode::InvalidEscape => "E0005",
+            ErrorCode::UnterminatedString => "E0006",
+            ErrorCode::InvalidNumber => "E0007",
+            ErrorCode::UnexpectedToken => "E0100",
+            ErrorCode::OutsideOfLoop => "E0101",
+            ErrorCode::MisplacedInnerDoc => "E0102",
+            ErrorCode::InvalidExpressionToken => "E0200",
+            ErrorCode::UnbalancedParens => "E0201",
+            ErrorCode::MissingOperand => "E0202",
+            ErrorCode::UnsupportedOperation => "E0203",
+            ErrorCode::DivisionByZero => "E0204",
+            ErrorCode::ConstantOverflow => "E0205",
+            ErrorCode::NotAssignable => "E0206",
+            ErrorCode::MismatchedTypes => "E0207",
+            ErrorCode::NegativeShift => "E0208",
+            ErrorCode::ConfusableIdentifier => "W0001",
+            ErrorCode::MixedScriptIdentifier => "W0002",
+            ErrorCode::UnusedDocComment => "W0003",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Points at a piece of the source code and says something about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// Primary labels point at the cause of the problem, secondary ones give context.
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+    /// Adds a primary label.
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+    /// Adds a secondary label.
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+    /// The first primary label, if there is one.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+}
+
+/// A plain one line version without any source code.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+
+        write!(f, ": {}", self.message)?;
+
+        if let Some(span) = self.primary_span() {
+            write!(f, " (at {}:{})", span.line, span.col)?;
+        }
+
+        for note in &self.notes {
+            write!(f, "\n  = note: {}", note)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects diagnostics from every stage of the compiler.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.list.push(diagnostic)
+    }
+    /// Moves everything from `other` into this one.
+    pub fn append(&mut self, other: &mut Diagnostics) {
+        self.list.append(&mut other.list)
+    }
+    pub fn error_count(&self) -> usize {
+        self.list
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+    pub fn has_errors(&self) -> bool {
+        self.error_count() != 0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.list.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::FileId;
+
+    #[test]
+    fn test_diagnostic_display() {
+        let d = Diagnostic::error("missing quote")
+            .with_code(ErrorCode::UnterminatedChar)
+            .with_secondary(Span::new(FileId(0), 0, 1, 1, 1), "started here")
+            .with_label(Span::new(FileId(0), 2, 1, 3, 5), "expected '")
+            .with_note("character literals are written like 'a'");
+
+        assert_eq!(
+            d.to_string(),
+            "error[E0002]: missing quote (at 3:5)\n  = note: character literals are written like 'a'"
+        );
+    }
+    #[test]
+    fn test_diagnostics_error_count() {
+        let mut diagnostics = Diagnostics::new();
+
+        diagnostics.push(Diagnostic::warning("a"));
+        assert!(!diagnostics.has_errors());
+
+        diagnostics.push(Diagnostic::error("b"));
+        diagnostics.push(Diagnostic::error("c"));
+
+        assert_eq!(diagnostics.error_count(), 2);
+        assert_eq!(diagnostics.len(), 3);
+    }
+}
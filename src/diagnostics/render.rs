@@ -0,0 +1,342 @@
+//! Turns diagnostics into text that looks like what rustc prints:
+//!
+//! ```text
+//! error[E0100]: expected `(`, found `{`
+//!  --> hello.niff:1:9
+//!   |
+//! 1 | fn main {
+//!   |         ^ expected `(`
+//! ```
+
+use std::io::IsTerminal;
+
+use super::{Diagnostic, Label, Severity};
+use crate::span::SourceMap;
+
+/// When should the output be colored?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only if stderr is a terminal and `NO_COLOR` isn't set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Parses the value of `--color=...`.
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+    /// Decides if colors should actually be used when writing to stderr.
+    pub fn use_color(self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// ANSI escape codes.
+#[derive(Debug, Clone, Copy)]
+enum Style {
+    Bold,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "\x1b[1m",
+            Style::Red => "\x1b[1;31m",
+            Style::Yellow => "\x1b[1;33m",
+            Style::Blue => "\x1b[1;34m",
+        }
+    }
+}
+
+/// How many columns a tab takes when printing source lines.
+const TAB_WIDTH: usize = 4;
+
+pub struct Renderer<'a> {
+    sources: &'a SourceMap,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(sources: &'a SourceMap, color: bool) -> Self {
+        Self { sources, color }
+    }
+    fn paint(&self, text: &str, style: Style) -> String {
+        if self.color {
+            format!("{}{}\x1b[0m", style.code(), text)
+        } else {
+            text.to_string()
+        }
+    }
+    fn severity_style(severity: Severity) -> Style {
+        match severity {
+            Severity::Error => Style::Red,
+            Severity::Warning => Style::Yellow,
+        }
+    }
+    /// Renders one diagnostic, ending with a newline.
+    pub fn render(&self, d: &Diagnostic) -> String {
+        let mut out = String::new();
+        let style = Self::severity_style(d.severity);
+
+        // error[E0001]: message
+        let mut header = d.severity.to_string();
+        if let Some(code) = d.code {
+            header += &format!("[{}]", code);
+        }
+        out += &self.paint(&header, style);
+        out += &self.paint(&format!(": {}", d.message), Style::Bold);
+        out.push('\n');
+
+        let mut labels = d.labels.iter().collect::<Vec<_>>();
+        labels.sort_by_key(|l| (l.span.file.0, l.span.line, l.span.col));
+
+        let width = labels
+            .iter()
+            .map(|l| l.span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+
+        if let Some(span) = d.primary_span().or_else(|| labels.first().map(|l| l.span)) {
+            out += &format!(
+                "{}{} {}:{}:{}\n",
+                pad,
+                self.paint("-->", Style::Blue),
+                self.sources.get(span.file).name,
+                span.line,
+                span.col
+            );
+        }
+
+        if !labels.is_empty() {
+            out += &format!("{} {}\n", pad, self.paint("|", Style::Blue));
+        }
+
+        let mut last_line = None;
+
+        for label in &labels {
+            let line = label.span.line;
+
+            if last_line != Some((label.span.file, line)) {
+                if let Some((_, last)) = last_line {
+                    if line > last + 1 {
+                        out += &format!("{}\n", self.paint("...", Style::Blue));
+                    }
+                }
+
+                let text = self.source_line(label);
+                out += &format!(
+                    "{} {}\n",
+                    self.paint(&format!("{:>width$} |", line, width = width), Style::Blue),
+                    expand_tabs(text)
+                );
+
+                last_line = Some((label.span.file, line));
+            }
+
+            out += &self.underline(label, &pad, style);
+        }
+
+        if !labels.is_empty() && !d.notes.is_empty() {
+            out += &format!("{} {}\n", pad, self.paint("|", Style::Blue));
+        }
+
+        for note in &d.notes {
+            out += &format!(
+                "{} {} {} {}\n",
+                pad,
+                self.paint("=", Style::Blue),
+                self.paint("note:", Style::Bold),
+                note
+            );
+        }
+
+        out
+    }
+    /// Renders the "could not compile" line at the end.
+    pub fn render_summary(&self, name: &str, errors: usize) -> String {
+        format!(
+            "{}{}\n",
+            self.paint("error", Style::Red),
+            self.paint(
+                &format!(
+                    ": could not compile `{}` due to {} previous error{}",
+                    name,
+                    errors,
+                    if errors == 1 { "" } else { "s" }
+                ),
+                Style::Bold
+            )
+        )
+    }
+    /// The line the label starts on, without the line break.
+    fn source_line(&self, label: &Label) -> &'a str {
+        let src = &self.sources.get(label.span.file).src;
+
+        src.lines()
+            .nth(label.span.line.saturating_sub(1) as usize)
+            .unwrap_or("")
+    }
+    /// The line with the carets under the source line.
+    fn underline(&self, label: &Label, pad: &str, style: Style) -> String {
+        let text = self.source_line(label);
+        let start = (label.span.col as usize).saturating_sub(1);
+
+        // Columns are counted in characters, tabs get expanded when printing.
+        let indent = text.chars().take(start).map(display_width).sum::<usize>();
+
+        // Only the first line of a span is underlined.
+        let src = &self.sources.get(label.span.file).src;
+        let end = label.span.end().min(src.len());
+        let covered = src.get(label.span.offset..end).unwrap_or("");
+        let covered = covered.split('\n').next().unwrap_or("");
+        let len = covered.chars().map(display_width).sum::<usize>().max(1);
+
+        let (marker, style) = if label.primary {
+            ("^", style)
+        } else {
+            ("-", Style::Blue)
+        };
+
+        let mut mark = marker.repeat(len);
+        if !label.message.is_empty() {
+            mark += " ";
+            mark += &label.message;
+        }
+
+        format!(
+            "{} {} {}{}\n",
+            pad,
+            self.paint("|", Style::Blue),
+            " ".repeat(indent),
+            self.paint(&mark, style)
+        )
+    }
+}
+
+fn display_width(c: char) -> usize {
+    if c == '\t' {
+        TAB_WIDTH
+    } else {
+        1
+    }
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ErrorCode;
+    use crate::span::Span;
+
+    fn sources() -> (SourceMap, crate::span::FileId) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("hello.niff", "fn main {\n\tfoo(;\n}\n");
+
+        (sources, file)
+    }
+
+    #[test]
+    fn test_render_single_label() {
+        let (sources, file) = sources();
+        let d = Diagnostic::error("expected `(`, found `{`")
+            .with_code(ErrorCode::UnexpectedToken)
+            .with_label(Span::new(file, 8, 1, 1, 9), "expected `(`");
+
+        let out = Renderer::new(&sources, false).render(&d);
+
+        assert_eq!(
+            out,
+            "error[E0100]: expected `(`, found `{`
+ --> hello.niff:1:9
+  |
+1 | fn main {
+  |         ^ expected `(`
+"
+        );
+    }
+    #[test]
+    fn test_render_multiple_labels_tabs_and_notes() {
+        let (sources, file) = sources();
+        let d = Diagnostic::warning("something")
+            .with_label(Span::new(file, 15, 1, 2, 6), "here")
+            .with_secondary(Span::new(file, 0, 2, 1, 1), "")
+            .with_note("a note");
+
+        let out = Renderer::new(&sources, false).render(&d);
+
+        assert_eq!(
+            out,
+            "warning: something
+ --> hello.niff:2:6
+  |
+1 | fn main {
+  | --
+2 |     foo(;
+  |         ^ here
+  |
+  = note: a note
+"
+        );
+    }
+    #[test]
+    fn test_render_without_labels() {
+        let sources = SourceMap::new();
+        let d = Diagnostic::error("oh no").with_note("a note");
+
+        assert_eq!(
+            Renderer::new(&sources, false).render(&d),
+            "error: oh no\n = note: a note\n"
+        );
+    }
+    #[test]
+    fn test_render_with_color() {
+        let (sources, file) = sources();
+        let d = Diagnostic::error("oh no").with_label(Span::new(file, 0, 2, 1, 1), "here");
+
+        let out = Renderer::new(&sources, true).render(&d);
+
+        assert!(out.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: oh no\x1b[0m\n"));
+        assert!(out.contains("\x1b[1;31m^^ here\x1b[0m"));
+    }
+    #[test]
+    fn test_render_summary() {
+        let sources = SourceMap::new();
+        let r = Renderer::new(&sources, false);
+
+        assert_eq!(
+            r.render_summary("a.niff", 1),
+            "error: could not compile `a.niff` due to 1 previous error\n"
+        );
+        assert_eq!(
+            r.render_summary("a.niff", 2),
+            "error: could not compile `a.niff` due to 2 previous errors\n"
+        );
+    }
+    #[test]
+    fn test_color_choice_from_arg() {
+        assert_eq!(ColorChoice::from_arg("auto"), Some(ColorChoice::Auto));
+        assert_eq!(ColorChoice::from_arg("always"), Some(ColorChoice::Always));
+        assert_eq!(ColorChoice::from_arg("never"), Some(ColorChoice::Never));
+        assert_eq!(ColorChoice::from_arg("sometimes"), None);
+    }
+}
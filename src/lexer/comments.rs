@@ -1,31 +1,83 @@
 /// Functions to lex comments.
 ///
-/// Comments are just discarded and no interesting things are done with them.
-/// I don't plan on doing anything cool with comments in the future.
-use super::Lexer;
-
-pub fn skip_comments(l: &mut Lexer) -> bool {
-    if l.src.get(l.i) == Some('/').as_ref() {
-        if l.src.get(l.i + 1) == Some('/').as_ref() {
-            l.advance_with(2);
-            skip_line_comment(l);
-
-            return true;
-        } else if l.src.get(l.i + 1) == Some('*').as_ref() {
-            l.advance_with(2);
-            skip_block_comment(l);
-
-            return true;
+/// Comments are discarded, except for doc comments. `///` documents what comes after it and
+/// `//!` the whole file, they are turned into tokens so the parser can attach them to the AST.
+use super::{DocStyle, Lexer, Token, TokenKind};
+use crate::diagnostics::{Diagnostic, ErrorCode};
+use crate::span::Span;
+
+/// Skips a comment or lexes a doc comment. Returns false if there isn't a comment.
+pub fn lex_comment(l: &mut Lexer) -> bool {
+    if l.byte() != Some(b'/') {
+        return false;
+    }
+
+    match l.byte_at(1) {
+        Some(b'/') => {
+            // `////` is a normal comment again.
+            let style = match (l.byte_at(2), l.byte_at(3)) {
+                (Some(b'/'), Some(b'/')) => None,
+                (Some(b'/'), _) => Some(DocStyle::Outer),
+                (Some(b'!'), _) => Some(DocStyle::Inner),
+                _ => None,
+            };
+
+            match style {
+                Some(style) => lex_doc_comment(l, style),
+                None => {
+                    l.advance_with(2);
+                    skip_line_comment(l);
+                }
+            }
+
+            true
+        }
+        Some(b'*') => {
+            let start = l.here();
+
+            if !skip_block_comment(l) {
+                let span = l.span_from(start);
+                l.error(
+                    Diagnostic::error("unterminated block comment")
+                        .with_code(ErrorCode::UnterminatedBlockComment)
+                        .with_label(span, "no terminating \"*/\" found")
+                        .with_note("block comments can be nested, every `/*` needs a `*/`"),
+                );
+            }
+
+            true
         }
+        _ => false,
     }
+}
 
-    false
+// Lexes a `///` or `//!` comment. The line break isn't part of the text or the span.
+fn lex_doc_comment(l: &mut Lexer, style: DocStyle) {
+    let start = l.here();
+
+    l.advance_with(3);
+
+    let text_start = l.i;
+
+    while !matches!(l.byte(), Some(b'\n') | None) {
+        l.advance();
+    }
+
+    let text = l.src[text_start..l.i].trim_end_matches('\r');
+
+    l.add_token(Token {
+        kind: TokenKind::DocComment(style, text),
+        span: Span {
+            len: text_start + text.len() - start.offset,
+            ..start
+        },
+    });
 }
 
 // Skips a line comment.
 fn skip_line_comment(l: &mut Lexer) {
-    while l.len > l.i {
-        if l.src[l.i] == '\n' {
+    while let Some(c) = l.byte() {
+        if c == b'\n' {
             l.advance();
 
             return;
@@ -35,39 +87,52 @@ fn skip_line_comment(l: &mut Lexer) {
     }
 }
 
-// Skips a block comment.
-fn skip_block_comment(l: &mut Lexer) {
-    while l.len > l.i {
-        if l.src[l.i] == '*' && l.src[l.i + 1] == '/' {
-            l.advance_with(2);
+// Skips a block comment starting at its `/*`, and the ones inside it too. Returns false if
+// it never ends.
+fn skip_block_comment(l: &mut Lexer) -> bool {
+    let mut depth = 0;
 
-            return;
-        }
+    while let Some(c) = l.byte() {
+        match (c, l.byte_at(1)) {
+            (b'*', Some(b'/')) => {
+                l.advance_with(2);
+                depth -= 1;
 
-        l.advance();
+                if depth == 0 {
+                    return true;
+                }
+            }
+            (b'/', Some(b'*')) => {
+                l.advance_with(2);
+                depth += 1;
+            }
+            _ => l.advance(),
+        }
     }
 
-    panic!("syntax error: no terminating \"*/\" found")
+    false
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::{lexer, Lexer, Token, TokenKind};
+    use crate::diagnostics::Diagnostics;
+    use crate::lexer::{lexer, DocStyle, Lexer, Token, TokenKind};
+    use crate::span::{FileId, Span};
 
     #[test]
     fn test_skip_line_comment() {
         let c0 = "// hello world\na";
         let c1 = "// hello world\n";
 
-        let mut l0 = Lexer::new(c0);
-        let mut l1 = Lexer::new(c1);
+        let mut l0 = Lexer::new(c0, FileId(0));
+        let mut l1 = Lexer::new(c1, FileId(0));
 
         skip_line_comment(&mut l0);
         skip_line_comment(&mut l1);
 
-        assert_eq!(l0.src.get(l0.i), Some('a').as_ref(), "Test case 1 failed");
-        assert_eq!(l1.src.get(l1.i), None, "Test case 2 failed");
+        assert_eq!(l0.byte(), Some(b'a'), "Test case 1 failed");
+        assert_eq!(l1.byte(), None, "Test case 2 failed");
     }
     #[test]
     fn test_skip_block_comment() {
@@ -75,25 +140,25 @@ mod tests {
         let c1 = "/* hello\n world */b";
         let c2 = "/* hello world */";
 
-        let mut l0 = Lexer::new(c0);
-        let mut l1 = Lexer::new(c1);
-        let mut l2 = Lexer::new(c2);
+        let mut l0 = Lexer::new(c0, FileId(0));
+        let mut l1 = Lexer::new(c1, FileId(0));
+        let mut l2 = Lexer::new(c2, FileId(0));
 
         skip_block_comment(&mut l0);
         skip_block_comment(&mut l1);
         skip_block_comment(&mut l2);
 
-        assert_eq!(l0.src.get(l0.i), Some('a').as_ref(), "Test case 1 failed");
-        assert_eq!(l1.src.get(l1.i), Some('b').as_ref(), "Test case 2 failed");
-        assert_eq!(l2.src.get(l2.i), None, "Test case 3 failed");
+        assert_eq!(l0.byte(), Some(b'a'), "Test case 1 failed");
+        assert_eq!(l1.byte(), Some(b'b'), "Test case 2 failed");
+        assert_eq!(l2.byte(), None, "Test case 3 failed");
     }
     #[test]
     fn test_lexer_with_line_comments() {
         let c0 = "// hello world\n";
         let c1 = "// hello world\n;";
 
-        let t0 = lexer(c0);
-        let t1 = lexer(c1);
+        let t0 = lexer(c0, FileId(0), &mut Diagnostics::new());
+        let t1 = lexer(c1, FileId(0), &mut Diagnostics::new());
 
         let o0 = format!("{:?}", t0);
         let o1 = format!("{:?}", t1);
@@ -102,7 +167,7 @@ mod tests {
             "{:?}",
             vec![Token {
                 kind: TokenKind::Eof,
-                size: 0
+                span: Span::new(FileId(0), 15, 0, 2, 1)
             }]
         );
         let e1 = format!(
@@ -110,11 +175,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::Semicolon,
-                    size: 1
+                    span: Span::new(FileId(0), 15, 1, 2, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 16, 0, 2, 2)
                 }
             ]
         );
@@ -128,9 +193,9 @@ mod tests {
         let c1 = "/* hello world */;";
         let c2 = "/* hello\n world */;";
 
-        let t0 = lexer(c0);
-        let t1 = lexer(c1);
-        let t2 = lexer(c2);
+        let t0 = lexer(c0, FileId(0), &mut Diagnostics::new());
+        let t1 = lexer(c1, FileId(0), &mut Diagnostics::new());
+        let t2 = lexer(c2, FileId(0), &mut Diagnostics::new());
 
         let o0 = format!("{:?}", t0);
         let o1 = format!("{:?}", t1);
@@ -140,7 +205,7 @@ mod tests {
             "{:?}",
             vec![Token {
                 kind: TokenKind::Eof,
-                size: 0
+                span: Span::new(FileId(0), 17, 0, 1, 18)
             }]
         );
         let e1 = format!(
@@ -148,11 +213,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::Semicolon,
-                    size: 1
+                    span: Span::new(FileId(0), 17, 1, 1, 18)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 18, 0, 1, 19)
                 }
             ]
         );
@@ -161,11 +226,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::Semicolon,
-                    size: 1
+                    span: Span::new(FileId(0), 18, 1, 2, 10)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 19, 0, 2, 11)
                 }
             ]
         );
@@ -174,4 +239,70 @@ mod tests {
         assert_eq!(o1, e1, "Test case 2 failed");
         assert_eq!(o2, e2, "Test case 3 failed");
     }
+    #[test]
+    fn test_lexer_with_unterminated_block_comment() {
+        let mut diagnostics = Diagnostics::new();
+
+        let tokens = lexer("; /* hello *", FileId(0), &mut diagnostics);
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics.iter().next().unwrap().primary_span(),
+            Some(Span::new(FileId(0), 2, 10, 1, 3))
+        );
+    }
+    #[test]
+    fn test_skip_nested_block_comment() {
+        let c0 = "/* a /* b */ c */d";
+        let c1 = "/* /* /* */ */ */e";
+        let c2 = "/* a /* b */ c";
+
+        let mut l0 = Lexer::new(c0, FileId(0));
+        let mut l1 = Lexer::new(c1, FileId(0));
+        let mut l2 = Lexer::new(c2, FileId(0));
+
+        assert!(skip_block_comment(&mut l0), "Test case 1 failed");
+        assert!(skip_block_comment(&mut l1), "Test case 2 failed");
+        assert!(!skip_block_comment(&mut l2), "Test case 3 failed");
+
+        assert_eq!(l0.byte(), Some(b'd'), "Test case 1 failed");
+        assert_eq!(l1.byte(), Some(b'e'), "Test case 2 failed");
+        assert_eq!(l2.byte(), None, "Test case 3 failed");
+    }
+    #[test]
+    fn test_lexer_with_doc_comments() {
+        let code = "//! The file\n/// Does things\r\n///\nfn //// not a doc\n// nor this\n/**/";
+
+        let tokens = lexer(code, FileId(0), &mut Diagnostics::new());
+
+        let a = format!("{:?}", tokens);
+        let b = format!(
+            "{:?}",
+            vec![
+                Token {
+                    kind: TokenKind::DocComment(DocStyle::Inner, " The file"),
+                    span: Span::new(FileId(0), 0, 12, 1, 1)
+                },
+                Token {
+                    kind: TokenKind::DocComment(DocStyle::Outer, " Does things"),
+                    span: Span::new(FileId(0), 13, 15, 2, 1)
+                },
+                Token {
+                    kind: TokenKind::DocComment(DocStyle::Outer, ""),
+                    span: Span::new(FileId(0), 30, 3, 3, 1)
+                },
+                Token {
+                    kind: TokenKind::Fn,
+                    span: Span::new(FileId(0), 34, 2, 4, 1)
+                },
+                Token {
+                    kind: TokenKind::Eof,
+                    span: Span::new(FileId(0), 68, 0, 6, 5)
+                }
+            ]
+        );
+
+        assert_eq!(a, b);
+    }
 }
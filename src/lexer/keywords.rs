@@ -1,345 +1,212 @@
 use super::*;
 
-// TODO: Make this function more organized. (Split into multiple functions/macros or something.)
-/// Checks if there is any keywords.
-/// Returns false if not, true if one was found.
+/// Every keyword and the token it turns into. Words are lexed as a whole first and then looked
+/// up here, so `iffy` is an identifier and not `if` followed by `fy`.
 ///
-/// If you're wondering where the token went, it was added to the tokens list in the Lexer struct.
-pub fn lex_keyword(l: &mut Lexer) -> bool {
-    // "fn" + space
-    if l.src.get(l.i) == Some('f').as_ref()
-        && l.src.get(l.i + 1) == Some('n').as_ref()
-        && l.src.get(l.i + 2) == Some(' ').as_ref()
-    {
-        l.advance_with(3);
-
-        l.add_token(Token {
-            kind: TokenKind::Fn,
-            size: 3,
-        });
-
-        return true;
-    }
-
-    // "if"
-    if l.src.get(l.i) == Some('i').as_ref() && l.src.get(l.i + 1) == Some('f').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::If,
-            size: 2,
-        });
-
-        return true;
-    }
-
-    // "elif"
-    if l.src.get(l.i) == Some('e').as_ref()
-        && l.src.get(l.i + 1) == Some('l').as_ref()
-        && l.src.get(l.i + 2) == Some('i').as_ref()
-        && l.src.get(l.i + 3) == Some('f').as_ref()
-    {
-        l.advance_with(4);
-
-        l.add_token(Token {
-            kind: TokenKind::Elif,
-            size: 4,
-        });
-
-        return true;
-    }
-
-    // "else"
-    if l.src.get(l.i) == Some('e').as_ref()
-        && l.src.get(l.i + 1) == Some('l').as_ref()
-        && l.src.get(l.i + 2) == Some('s').as_ref()
-        && l.src.get(l.i + 3) == Some('e').as_ref()
-    {
-        l.advance_with(4);
-
-        l.add_token(Token {
-            kind: TokenKind::Else,
-            size: 4,
-        });
-
-        return true;
-    }
-
-    // "for"
-    if l.src.get(l.i) == Some('f').as_ref()
-        && l.src.get(l.i + 1) == Some('o').as_ref()
-        && l.src.get(l.i + 2) == Some('r').as_ref()
-    {
-        l.advance_with(3);
-
-        l.add_token(Token {
-            kind: TokenKind::For,
-            size: 3,
-        });
-
-        return true;
-    }
-
-    // "while"
-    if l.src.get(l.i) == Some('w').as_ref()
-        && l.src.get(l.i + 1) == Some('h').as_ref()
-        && l.src.get(l.i + 2) == Some('i').as_ref()
-        && l.src.get(l.i + 3) == Some('l').as_ref()
-        && l.src.get(l.i + 4) == Some('e').as_ref()
-    {
-        l.advance_with(5);
-
-        l.add_token(Token {
-            kind: TokenKind::While,
-            size: 5,
-        });
-
-        return true;
-    }
-
-    // "return"
-    if l.src.get(l.i) == Some('r').as_ref()
-        && l.src.get(l.i + 1) == Some('e').as_ref()
-        && l.src.get(l.i + 2) == Some('t').as_ref()
-        && l.src.get(l.i + 3) == Some('u').as_ref()
-        && l.src.get(l.i + 4) == Some('r').as_ref()
-        && l.src.get(l.i + 5) == Some('n').as_ref()
-    {
-        l.advance_with(6);
-
-        l.add_token(Token {
-            kind: TokenKind::Return,
-            size: 6,
-        });
-
-        return true;
-    }
-
-    // "var"
-    if l.src.get(l.i) == Some('v').as_ref()
-        && l.src.get(l.i + 1) == Some('a').as_ref()
-        && l.src.get(l.i + 2) == Some('r').as_ref()
-    {
-        l.advance_with(4);
-
-        l.add_token(Token {
-            kind: TokenKind::Var,
-            size: 4,
-        });
-
-        return true;
-    }
-
-    // "false"
-    // TODO: if you have an identifier "falseasd", it will result in tokens: False and Unknown("asd"),
-    // instead of the intended: Unknown("falseasd")
-    if l.src.get(l.i) == Some('f').as_ref()
-        && l.src.get(l.i + 1) == Some('a').as_ref()
-        && l.src.get(l.i + 2) == Some('l').as_ref()
-        && l.src.get(l.i + 3) == Some('s').as_ref()
-        && l.src.get(l.i + 4) == Some('e').as_ref()
-    {
-        l.advance_with(5);
-
-        l.add_token(Token {
-            kind: TokenKind::False,
-            size: 5,
-        });
-
-        return true;
-    }
-
-    // "true"
-    // TODO: if you have an identifier "trueasd", it will result in tokens: True and Unknown("asd"),
-    // instead of the intended: Unknown("trueasd")
-    if l.src.get(l.i) == Some('t').as_ref()
-        && l.src.get(l.i + 1) == Some('r').as_ref()
-        && l.src.get(l.i + 2) == Some('u').as_ref()
-        && l.src.get(l.i + 3) == Some('e').as_ref()
-    {
-        l.advance_with(4);
-
-        l.add_token(Token {
-            kind: TokenKind::True,
-            size: 4,
-        });
-
-        return true;
-    }
-
-    false
+/// Types are here too, they can't be used as identifiers either.
+pub const KEYWORDS: &[(&str, TokenKind<'static>)] = &[
+    ("fn", TokenKind::Fn),
+    ("if", TokenKind::If),
+    ("elif", TokenKind::Elif),
+    ("else", TokenKind::Else),
+    ("for", TokenKind::For),
+    ("while", TokenKind::While),
+    ("return", TokenKind::Return),
+    ("var", TokenKind::Var),
+    ("mut", TokenKind::Mut),
+    ("const", TokenKind::Const),
+    ("break", TokenKind::Break),
+    ("continue", TokenKind::Continue),
+    ("in", TokenKind::In),
+    ("struct", TokenKind::Struct),
+    ("false", TokenKind::False),
+    ("true", TokenKind::True),
+    ("void", TokenKind::Type(Type::Void)),
+    ("i32", TokenKind::Type(Type::I32)),
+    ("u32", TokenKind::Type(Type::U32)),
+    ("u8", TokenKind::Type(Type::U8)),
+    ("string", TokenKind::Type(Type::String)),
+];
+
+/// Returns the token for `word` if it's a keyword.
+pub fn keyword(word: &str) -> Option<TokenKind<'static>> {
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| *keyword == word)
+        .map(|(_, kind)| kind.clone())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::other::lex_unknown_or_ident;
     use super::super::Lexer;
     use super::*;
 
     #[test]
     fn test_lex_keyword_fn() {
         let src = "fn main() {}";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Fn,
-                size: 3
+                span: Span::new(FileId(0), 0, 2, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], 'm');
+        assert_eq!(lexer.byte(), Some(b' '));
     }
     #[test]
     fn test_lex_keyword_if() {
         let src = "if(a == b) {}";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::If,
-                size: 2
+                span: Span::new(FileId(0), 0, 2, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '(');
+        assert_eq!(lexer.byte(), Some(b'('));
     }
     #[test]
     fn test_lex_keyword_elif() {
         let src = "elif(a == b) {}";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Elif,
-                size: 4
+                span: Span::new(FileId(0), 0, 4, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '(');
+        assert_eq!(lexer.byte(), Some(b'('));
     }
     #[test]
     fn test_lex_keyword_else() {
         let src = "else {}";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Else,
-                size: 4
+                span: Span::new(FileId(0), 0, 4, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], ' ');
+        assert_eq!(lexer.byte(), Some(b' '));
     }
     #[test]
     fn test_lex_keyword_for() {
         let src = "for(a = 1; b < 2; a++) {}";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::For,
-                size: 3
+                span: Span::new(FileId(0), 0, 3, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '(');
+        assert_eq!(lexer.byte(), Some(b'('));
     }
     #[test]
     fn test_lex_keyword_while() {
         let src = "while(a == b) {}";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::While,
-                size: 5
+                span: Span::new(FileId(0), 0, 5, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '(');
+        assert_eq!(lexer.byte(), Some(b'('));
     }
     #[test]
     fn test_lex_keyword_return() {
         let src = "return a;";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Return,
-                size: 6
+                span: Span::new(FileId(0), 0, 6, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], ' ');
+        assert_eq!(lexer.byte(), Some(b' '));
     }
     #[test]
     fn test_lex_keyword_var() {
         let src = "var something = 3;";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Var,
-                size: 4
+                span: Span::new(FileId(0), 0, 3, 1, 1)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], 's');
+        assert_eq!(lexer.byte(), Some(b' '));
     }
     #[test]
     fn test_lex_keyword_false() {
         let src = "false";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::False,
-                size: 5
+                span: Span::new(FileId(0), 0, 5, 1, 1)
             }
         );
 
@@ -348,19 +215,173 @@ mod tests {
     #[test]
     fn test_lex_keyword_true() {
         let src = "true";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
-        lex_keyword(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::True,
-                size: 4
+                span: Span::new(FileId(0), 0, 4, 1, 1)
+            }
+        );
+
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn test_lex_keyword_mut() {
+        let src = "mut a i32";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
+        let b = format!(
+            "{:?}",
+            Token {
+                kind: TokenKind::Mut,
+                span: Span::new(FileId(0), 0, 3, 1, 1)
+            }
+        );
+
+        assert_eq!(a, b);
+        assert_eq!(lexer.byte(), Some(b' '));
+    }
+    #[test]
+    fn test_lex_keyword_const() {
+        let src = "const a = 1;";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
+        let b = format!(
+            "{:?}",
+            Token {
+                kind: TokenKind::Const,
+                span: Span::new(FileId(0), 0, 5, 1, 1)
+            }
+        );
+
+        assert_eq!(a, b);
+        assert_eq!(lexer.byte(), Some(b' '));
+    }
+    #[test]
+    fn test_lex_keyword_break() {
+        let src = "break;";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
+        let b = format!(
+            "{:?}",
+            Token {
+                kind: TokenKind::Break,
+                span: Span::new(FileId(0), 0, 5, 1, 1)
+            }
+        );
+
+        assert_eq!(a, b);
+        assert_eq!(lexer.byte(), Some(b';'));
+    }
+    #[test]
+    fn test_lex_keyword_continue() {
+        let src = "continue;";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
+        let b = format!(
+            "{:?}",
+            Token {
+                kind: TokenKind::Continue,
+                span: Span::new(FileId(0), 0, 8, 1, 1)
+            }
+        );
+
+        assert_eq!(a, b);
+        assert_eq!(lexer.byte(), Some(b';'));
+    }
+    #[test]
+    fn test_lex_keyword_in() {
+        let src = "in 0..10";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
+        let b = format!(
+            "{:?}",
+            Token {
+                kind: TokenKind::In,
+                span: Span::new(FileId(0), 0, 2, 1, 1)
             }
         );
 
         assert_eq!(a, b);
+        assert_eq!(lexer.byte(), Some(b' '));
+    }
+    #[test]
+    fn test_lex_keyword_struct() {
+        let src = "struct Foo {}";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
+        let b = format!(
+            "{:?}",
+            Token {
+                kind: TokenKind::Struct,
+                span: Span::new(FileId(0), 0, 6, 1, 1)
+            }
+        );
+
+        assert_eq!(a, b);
+        assert_eq!(lexer.byte(), Some(b' '));
+    }
+    #[test]
+    fn test_keywords_are_whole_words() {
+        let src = "iffy format variable elsewhere fn\tmain truer _if";
+        let tokens = lexer(src, FileId(0), &mut Diagnostics::new());
+
+        let a = format!(
+            "{:?}",
+            tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+        );
+        let b = format!(
+            "{:?}",
+            vec![
+                TokenKind::Unknown("iffy".into()),
+                TokenKind::Unknown("format".into()),
+                TokenKind::Unknown("variable".into()),
+                TokenKind::Unknown("elsewhere".into()),
+                TokenKind::Fn,
+                TokenKind::Unknown("main".into()),
+                TokenKind::Unknown("truer".into()),
+                TokenKind::Unknown("_if".into()),
+                TokenKind::Eof,
+            ]
+        );
+
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn test_keyword() {
+        for (word, kind) in KEYWORDS {
+            assert_eq!(
+                keyword(word).as_ref(),
+                Some(kind),
+                "Test case {} failed",
+                word
+            );
+        }
+
+        assert_eq!(keyword("main"), None);
+        assert_eq!(keyword("Fn"), None);
     }
 }
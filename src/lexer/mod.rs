@@ -3,30 +3,29 @@ mod keywords;
 mod other;
 mod sequences;
 mod strings;
+mod unicode;
 
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::FusedIterator;
+
+use crate::diagnostics::{Diagnostic, Diagnostics, ErrorCode};
 use crate::parser::Type;
+use crate::span::{FileId, Span};
 
-#[derive(Debug)]
-pub struct Token {
+#[derive(Debug, Clone)]
+pub struct Token<'src> {
     /// What token is it?
-    pub kind: TokenKind,
-    /// How many bytes/characters was consumed to obtain it. Not used currently.
-    pub size: usize,
-}
-
-impl From<(TokenKind, usize)> for Token {
-    fn from(tuple: (TokenKind, usize)) -> Self {
-        Self {
-            kind: tuple.0,
-            size: tuple.1,
-        }
-    }
+    pub kind: TokenKind<'src>,
+    /// Where is it?
+    pub span: Span,
 }
 
 // A token.
 #[derive(Debug, Clone)]
 #[repr(u8)]
-pub enum TokenKind {
+pub enum TokenKind<'src> {
     /// "="
     Eq,
     /// "+"
@@ -95,20 +94,32 @@ pub enum TokenKind {
     Mut,
     /// "const"
     Const,
+    /// "break"
+    Break,
+    /// "continue"
+    Continue,
+    /// "in"
+    In,
+    /// "struct"
+    Struct,
     /// "false"
     False,
     /// "true"
     True,
-    /// An integer literal (value)
-    IntegerLiteral(i64),
+    /// An integer literal (value, type suffix or `Type::None`). The value of a signed type can be
+    /// one larger than its maximum, so `-2147483648i32` can be written. The parser checks that.
+    IntegerLiteral(u64, Type),
     /// A float literal (value)
     FloatLiteral(f64),
-    /// A string literal (data)
-    StringLiteral(String),
+    /// A string literal (data). Borrowed from the source unless it had escapes in it.
+    StringLiteral(Cow<'src, str>),
     /// A char literal (character)
     CharLiteral(char),
-    /// Unknown or identifier (identifier/unknown token)
-    Unknown(String),
+    /// A `///` or `//!` comment (style, text after the `///` or `//!`)
+    DocComment(DocStyle, &'src str),
+    /// Unknown or identifier (identifier/unknown token). Borrowed from the source unless NFC
+    /// normalization changed it.
+    Unknown(Cow<'src, str>),
     /// "::"
     DoubleColon,
     /// "=="
@@ -125,79 +136,260 @@ pub enum TokenKind {
     FatArrow,
     /// "->"
     Arrow,
+    /// "..="
+    DoubleDotEq,
+    /// "||"
+    DoublePipe,
+    /// "&&"
+    DoubleAnd,
+    /// "<<"
+    ShiftLeft,
+    /// ">>"
+    ShiftRight,
+    /// "**"
+    DoubleStar,
+    /// "~"
+    Tilde,
+    /// "++"
+    DoublePlus,
+    /// "--"
+    DoubleMinus,
+    /// "+="
+    PlusEq,
+    /// "-="
+    MinusEq,
+    /// "*="
+    StarEq,
+    /// "/="
+    SlashEq,
+    /// "%="
+    PercentEq,
+    /// "&="
+    AndEq,
+    /// "|="
+    PipeEq,
+    /// "^="
+    CaretEq,
+    /// "<<="
+    ShiftLeftEq,
+    /// ">>="
+    ShiftRightEq,
     /// Type
     Type(Type),
     /// End of input
     Eof,
 }
 
-impl PartialEq for TokenKind {
-    /// Note: this does some things that probably don't expect. Please read the code.
+/// Which kind of doc comment it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocStyle {
+    /// `///`, documents what comes after it.
+    Outer,
+    /// `//!`, documents the file it's in.
+    Inner,
+}
+
+/// How the token is described in error messages.
+impl fmt::Display for TokenKind<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TokenKind::Eq => "`=`",
+            TokenKind::Plus => "`+`",
+            TokenKind::Minus => "`-`",
+            TokenKind::Percent => "`%`",
+            TokenKind::LessThan => "`<`",
+            TokenKind::GreaterThan => "`>`",
+            TokenKind::Slash => "`/`",
+            TokenKind::Star => "`*`",
+            TokenKind::And => "`&`",
+            TokenKind::Caret => "`^`",
+            TokenKind::Pipe => "`|`",
+            TokenKind::Question => "`?`",
+            TokenKind::Bang => "`!`",
+            TokenKind::Semicolon => "`;`",
+            TokenKind::Colon => "`:`",
+            TokenKind::LeftParen => "`(`",
+            TokenKind::RightParen => "`)`",
+            TokenKind::LeftBrace => "`[`",
+            TokenKind::RightBrace => "`]`",
+            TokenKind::LeftBracket => "`{`",
+            TokenKind::RightBracket => "`}`",
+            TokenKind::Comma => "`,`",
+            TokenKind::Dot => "`.`",
+            TokenKind::Quote => "`'`",
+            TokenKind::Var => "`var`",
+            TokenKind::Fn => "`fn`",
+            TokenKind::If => "`if`",
+            TokenKind::Elif => "`elif`",
+            TokenKind::Else => "`else`",
+            TokenKind::While => "`while`",
+            TokenKind::For => "`for`",
+            TokenKind::Return => "`return`",
+            TokenKind::Mut => "`mut`",
+            TokenKind::Const => "`const`",
+            TokenKind::Break => "`break`",
+            TokenKind::Continue => "`continue`",
+            TokenKind::In => "`in`",
+            TokenKind::Struct => "`struct`",
+            TokenKind::False => "`false`",
+            TokenKind::True => "`true`",
+            TokenKind::IntegerLiteral(a, Type::None) => return write!(f, "integer `{}`", a),
+            TokenKind::IntegerLiteral(a, ty) => return write!(f, "integer `{}{}`", a, ty),
+            TokenKind::FloatLiteral(a) => return write!(f, "float `{}`", a),
+            TokenKind::StringLiteral(_) => "string literal",
+            TokenKind::CharLiteral(a) => return write!(f, "character {:?}", a),
+            TokenKind::DocComment(..) => "doc comment",
+            TokenKind::Unknown(a) => return write!(f, "identifier `{}`", a),
+            TokenKind::DoubleColon => "`::`",
+            TokenKind::DoubleEquals => "`==`",
+            TokenKind::BangEquals => "`!=`",
+            TokenKind::GtEq => "`>=`",
+            TokenKind::LtEq => "`<=`",
+            TokenKind::DoubleDot => "`..`",
+            TokenKind::FatArrow => "`=>`",
+            TokenKind::Arrow => "`->`",
+            TokenKind::DoubleDotEq => "`..=`",
+            TokenKind::DoublePipe => "`||`",
+            TokenKind::DoubleAnd => "`&&`",
+            TokenKind::ShiftLeft => "`<<`",
+            TokenKind::ShiftRight => "`>>`",
+            TokenKind::DoubleStar => "`**`",
+            TokenKind::Tilde => "`~`",
+            TokenKind::DoublePlus => "`++`",
+            TokenKind::DoubleMinus => "`--`",
+            TokenKind::PlusEq => "`+=`",
+            TokenKind::MinusEq => "`-=`",
+            TokenKind::StarEq => "`*=`",
+            TokenKind::SlashEq => "`/=`",
+            TokenKind::PercentEq => "`%=`",
+            TokenKind::AndEq => "`&=`",
+            TokenKind::PipeEq => "`|=`",
+            TokenKind::CaretEq => "`^=`",
+            TokenKind::ShiftLeftEq => "`<<=`",
+            TokenKind::ShiftRightEq => "`>>=`",
+            TokenKind::Type(a) => return write!(f, "type `{}`", a),
+            TokenKind::Eof => "end of file",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl PartialEq for TokenKind<'_> {
+    /// Note: only the kind of the token is compared, not the value it carries.
+    /// `IntegerLiteral(1, Type::None) == IntegerLiteral(2, Type::U8)` is true.
     fn eq(&self, other: &Self) -> bool {
-        // TODO: implement for the rest of the enum members.
-        match (self, other) {
-            (TokenKind::Unknown(_), TokenKind::Unknown(_)) => true,
-            (TokenKind::Type(_), TokenKind::Type(_)) => true,
-            (TokenKind::IntegerLiteral(_), TokenKind::IntegerLiteral(_)) => true,
-            (TokenKind::FloatLiteral(_), TokenKind::FloatLiteral(_)) => true,
-            (TokenKind::StringLiteral(_), TokenKind::StringLiteral(_)) => true,
-            _ => matches!(
-                (self, other),
-                (TokenKind::Fn, TokenKind::Fn)
-                    | (TokenKind::Var, TokenKind::Var)
-                    | (TokenKind::Eq, TokenKind::Eq)
-                    | (TokenKind::Semicolon, TokenKind::Semicolon)
-                    | (TokenKind::Colon, TokenKind::Colon)
-                    | (TokenKind::Dot, TokenKind::Dot)
-                    | (TokenKind::Comma, TokenKind::Comma)
-                    | (TokenKind::Slash, TokenKind::Slash)
-                    | (TokenKind::Star, TokenKind::Star)
-                    | (TokenKind::LessThan, TokenKind::LessThan)
-                    | (TokenKind::GreaterThan, TokenKind::GreaterThan)
-                    | (TokenKind::LeftParen, TokenKind::LeftParen)
-                    | (TokenKind::RightParen, TokenKind::RightParen)
-                    | (TokenKind::LeftBrace, TokenKind::LeftBrace)
-                    | (TokenKind::RightBrace, TokenKind::RightBrace)
-                    | (TokenKind::LeftBracket, TokenKind::LeftBracket)
-                    | (TokenKind::RightBracket, TokenKind::RightBracket)
-                    | (TokenKind::Quote, TokenKind::Quote)
-                    | (TokenKind::Caret, TokenKind::Caret)
-                    | (TokenKind::And, TokenKind::And)
-                    | (TokenKind::Pipe, TokenKind::Pipe)
-                    | (TokenKind::Percent, TokenKind::Percent)
-                    | (TokenKind::DoubleEquals, TokenKind::DoubleEquals)
-                    | (TokenKind::BangEquals, TokenKind::BangEquals)
-                    | (TokenKind::GtEq, TokenKind::GtEq)
-                    | (TokenKind::LtEq, TokenKind::LtEq)
-                    | (TokenKind::Eof, TokenKind::Eof)
-            ),
-        }
+        std::mem::discriminant(self) == std::mem::discriminant(other)
     }
 }
 
+/// Errors are diagnostics, like in the parser. The lexer skips the bad input and keeps going
+/// after one. Warnings come through here too, see [`Lexer`].
+pub type LexError = Diagnostic;
+
 /// Stores information used by the lexer.
+///
+/// The lexer works on the bytes of the source. `i` is a byte offset and always on a character
+/// boundary, advance() steps over whole UTF-8 characters. Everything that matters to the syntax
+/// is ASCII, so most of the time only one byte has to be looked at.
+///
+/// It's an iterator of tokens and errors, and only lexes as far as it's asked to. The last token
+/// is always Eof, after that it returns None.
+///
+/// Not every `Err` is an error: lint warnings (like confusable identifiers) are yielded as `Err`
+/// too, with `Severity::Warning`. The token they're about is still yielded, so a warning never
+/// stands in for a token. Check the severity before treating an `Err` as a failure.
 #[derive(Debug)]
-pub struct Lexer {
+pub struct Lexer<'src> {
+    /// Byte offset of the "cursor".
     i: usize,
-    src: Vec<char>,
-    tokens: Vec<Token>,
+    src: &'src str,
+    /// Tokens and errors that were lexed but not taken yet, peek() looks at these.
+    queue: VecDeque<Result<Token<'src>, LexError>>,
+    /// Is Eof in the queue (or taken already)?
+    done: bool,
     len: usize,
+    /// Which file is being lexed.
+    file: FileId,
+    /// Line of the "cursor", starting from 1.
+    line: u32,
+    /// Column of the "cursor" in characters, starting from 1.
+    col: u32,
+    /// Identifiers seen so far, for the confusable identifier lint.
+    identifiers: unicode::Identifiers<'src>,
 }
 
-impl Lexer {
+impl<'src> Lexer<'src> {
     #[inline]
-    fn new(src: &str) -> Lexer {
+    /// A byte order mark at the start of `src` is skipped. Offsets still count it, so they
+    /// stay offsets into `src`.
+    pub fn new(src: &'src str, file: FileId) -> Lexer<'src> {
         Lexer {
-            i: 0,
-            src: src.chars().collect(),
-            tokens: Vec::new(),
+            i: if src.starts_with('\u{feff}') { 3 } else { 0 },
+            src,
+            queue: VecDeque::new(),
+            done: false,
             len: src.len(),
+            file,
+            line: 1,
+            col: 1,
+            identifiers: unicode::Identifiers::default(),
         }
     }
-    /// Advances the "cursor" by one.
+    /// The next token or error, without taking it.
+    pub fn peek(&mut self) -> Option<&Result<Token<'src>, LexError>> {
+        self.peek_nth(0)
+    }
+    /// The token or error `n` places after the next one, without taking anything. Errors take a
+    /// place too. Lexes as far ahead as needed.
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Result<Token<'src>, LexError>> {
+        while self.queue.len() <= n && !self.done {
+            lex_next(self);
+        }
+
+        self.queue.get(n)
+    }
+    /// The byte at the "cursor", None at the end of the file.
+    #[inline(always)]
+    fn byte(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.i).copied()
+    }
+    /// The byte `n` bytes after the "cursor".
+    #[inline(always)]
+    fn byte_at(&self, n: usize) -> Option<u8> {
+        self.src.as_bytes().get(self.i + n).copied()
+    }
+    /// The whole character at the "cursor", for when it might not be ASCII.
+    #[inline]
+    fn current_char(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    /// Everything from the "cursor" to the end of the file.
+    #[inline]
+    fn rest(&self) -> &'src str {
+        &self.src[self.i..]
+    }
+    /// Advances the "cursor" by one character.
     #[inline(always)]
     fn advance(&mut self) {
-        self.i += 1;
+        let Some(b) = self.byte() else {
+            return;
+        };
+
+        if b == b'\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+
+        // The length of a UTF-8 character can be told from its first byte.
+        self.i += match b {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
     }
     /// Advances the "cursor" by `val`.
     /// Use advance() when only advancing by one.
@@ -211,30 +403,59 @@ impl Lexer {
             "You should use advance() instead, when advancing only by one."
         );
 
-        self.i += val;
+        for _ in 0..val {
+            self.advance();
+        }
     }
-    /// Adds `token` to the `tokens` list on the object.
-    fn add_token(&mut self, token: Token) {
-        self.tokens.push(token)
+    /// An empty span at the "cursor". Save it before lexing a token and pass it to
+    /// span_from() afterwards.
+    fn here(&self) -> Span {
+        Span::new(self.file, self.i, 0, self.line, self.col)
+    }
+    /// A span from `start` up to the "cursor".
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            len: self.i - start.offset,
+            ..start
+        }
+    }
+    /// Adds `token` to the queue.
+    fn add_token(&mut self, token: Token<'src>) {
+        self.queue.push_back(Ok(token))
+    }
+    /// Reports an error. Lexing continues after this.
+    fn error(&mut self, diagnostic: LexError) {
+        self.queue.push_back(Err(diagnostic))
+    }
+    /// Adds a token that is one character long and advances past it.
+    fn add_single(&mut self, kind: TokenKind<'src>) {
+        let start = self.here();
+
+        self.advance();
+
+        self.add_token(Token {
+            kind,
+            span: self.span_from(start),
+        });
     }
     /// Is `c` a whitespace character?
     #[inline]
-    fn is_whitespace(c: char) -> bool {
+    fn is_whitespace(c: u8) -> bool {
         matches!(
             c,
-            ' '
-            | '\t'
-            | '\n'
-            | '\r'
-            | '\u{000B}' // vertical tab
-            | '\u{000C}' // form feed
+            b' '
+            | b'\t'
+            | b'\n'
+            | b'\r'
+            | 0x0B // vertical tab
+            | 0x0C // form feed
         )
     }
     /// Skips whitespace characters.
     #[inline]
     fn skip_whitespace(&mut self) {
-        while self.len > self.i {
-            if Lexer::is_whitespace(self.src[self.i]) {
+        while let Some(c) = self.byte() {
+            if Lexer::is_whitespace(c) {
                 self.advance();
             } else {
                 break;
@@ -243,167 +464,185 @@ impl Lexer {
     }
 }
 
-pub fn lexer(src: &str) -> Vec<Token> {
-    let mut lexer = Lexer::new(src);
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Result<Token<'src>, LexError>;
 
-    while lexer.len != lexer.i {
-        // whitespaces
-        lexer.skip_whitespace();
+    fn next(&mut self) -> Option<Self::Item> {
+        self.peek();
+        self.queue.pop_front()
+    }
+}
 
-        // comments
-        if comments::skip_comments(&mut lexer) {
-            continue;
-        }
+impl FusedIterator for Lexer<'_> {}
 
-        // keywords
-        if keywords::lex_keyword(&mut lexer) {
-            continue;
-        }
+/// Lexes the next token, or skips a comment. Tokens and errors are added to the queue, and the
+/// Eof token once the end of the file is reached.
+fn lex_next(lexer: &mut Lexer) {
+    // whitespaces
+    lexer.skip_whitespace();
 
-        // sequences
-        if sequences::lex_sequence(&mut lexer) {
-            continue;
-        }
+    if lexer.len <= lexer.i {
+        lexer.add_token(Token {
+            kind: TokenKind::Eof,
+            span: lexer.here(),
+        });
+        lexer.done = true;
+
+        return;
+    }
 
-        // character literals
-        if strings::lex_char(&mut lexer) {
-            continue;
+    // comments
+    if comments::lex_comment(lexer) {
+        return;
+    }
+
+    // sequences
+    if sequences::lex_sequence(lexer) {
+        return;
+    }
+
+    // character literals
+    if strings::lex_char(lexer) {
+        return;
+    }
+
+    // other
+    match lexer.byte() {
+        Some(b'=') => {
+            lexer.add_single(TokenKind::Eq);
+        }
+        Some(b'(') => {
+            lexer.add_single(TokenKind::LeftParen);
+        }
+        Some(b')') => {
+            lexer.add_single(TokenKind::RightParen);
+        }
+        Some(b'[') => {
+            lexer.add_single(TokenKind::LeftBrace);
+        }
+        Some(b']') => {
+            lexer.add_single(TokenKind::RightBrace);
+        }
+        Some(b'{') => {
+            lexer.add_single(TokenKind::LeftBracket);
+        }
+        Some(b'}') => {
+            lexer.add_single(TokenKind::RightBracket);
+        }
+        Some(b'!') => {
+            lexer.add_single(TokenKind::Bang);
+        }
+        Some(b'.') => {
+            lexer.add_single(TokenKind::Dot);
+        }
+        Some(b',') => {
+            lexer.add_single(TokenKind::Comma);
+        }
+        Some(b';') => {
+            lexer.add_single(TokenKind::Semicolon);
+        }
+        Some(b':') => {
+            lexer.add_single(TokenKind::Colon);
+        }
+        Some(b'\'') => {
+            lexer.add_single(TokenKind::Quote);
+        }
+        Some(b'*') => {
+            lexer.add_single(TokenKind::Star);
         }
+        Some(b'/') => {
+            lexer.add_single(TokenKind::Slash);
+        }
+        Some(b'<') => {
+            lexer.add_single(TokenKind::LessThan);
+        }
+        Some(b'>') => {
+            lexer.add_single(TokenKind::GreaterThan);
+        }
+        Some(b'?') => {
+            lexer.add_single(TokenKind::Question);
+        }
+        Some(b'+') => {
+            lexer.add_single(TokenKind::Plus);
+        }
+        Some(b'-') => {
+            lexer.add_single(TokenKind::Minus);
+        }
+        Some(b'^') => {
+            lexer.add_single(TokenKind::Caret);
+        }
+        Some(b'|') => {
+            lexer.add_single(TokenKind::Pipe);
+        }
+        Some(b'&') => {
+            lexer.add_single(TokenKind::And);
+        }
+        Some(b'%') => {
+            lexer.add_single(TokenKind::Percent);
+        }
+        Some(b'~') => {
+            lexer.add_single(TokenKind::Tilde);
+        }
+        Some(_) => {
+            // Unknown or identifier, keyword and type
+            let before = lexer.i;
+
+            other::lex_number(lexer);
+            strings::lex_string(lexer);
+            other::lex_unknown_or_ident(lexer);
+
+            // Nothing wanted it, skip it so we don't get stuck.
+            if lexer.i == before {
+                let c = lexer.current_char().unwrap_or(strings::REPLACEMENT);
+                let start = lexer.here();
 
-        // other
-        match lexer.src.get(lexer.i) {
-            Some('=') => {
-                lexer.add_token((TokenKind::Eq, 1).into());
-                lexer.advance();
-            }
-            Some('(') => {
-                lexer.add_token((TokenKind::LeftParen, 1).into());
-                lexer.advance();
-            }
-            Some(')') => {
-                lexer.add_token((TokenKind::RightParen, 1).into());
-                lexer.advance();
-            }
-            Some('[') => {
-                lexer.add_token((TokenKind::LeftBrace, 1).into());
-                lexer.advance();
-            }
-            Some(']') => {
-                lexer.add_token((TokenKind::RightBrace, 1).into());
-                lexer.advance();
-            }
-            Some('{') => {
-                lexer.add_token((TokenKind::LeftBracket, 1).into());
-                lexer.advance();
-            }
-            Some('}') => {
-                lexer.add_token((TokenKind::RightBracket, 1).into());
-                lexer.advance();
-            }
-            Some('!') => {
-                lexer.add_token((TokenKind::Bang, 1).into());
-                lexer.advance();
-            }
-            Some('.') => {
-                lexer.add_token((TokenKind::Dot, 1).into());
-                lexer.advance();
-            }
-            Some(',') => {
-                lexer.add_token((TokenKind::Comma, 1).into());
-                lexer.advance();
-            }
-            Some(';') => {
-                lexer.add_token((TokenKind::Semicolon, 1).into());
-                lexer.advance();
-            }
-            Some(':') => {
-                lexer.add_token((TokenKind::Colon, 1).into());
-                lexer.advance();
-            }
-            Some('\'') => {
-                lexer.add_token((TokenKind::Quote, 1).into());
-                lexer.advance();
-            }
-            Some('*') => {
-                lexer.add_token((TokenKind::Star, 1).into());
-                lexer.advance();
-            }
-            Some('/') => {
-                lexer.add_token((TokenKind::Slash, 1).into());
-                lexer.advance();
-            }
-            Some('<') => {
-                lexer.add_token((TokenKind::LessThan, 1).into());
-                lexer.advance();
-            }
-            Some('>') => {
-                lexer.add_token((TokenKind::GreaterThan, 1).into());
-                lexer.advance();
-            }
-            Some('?') => {
-                lexer.add_token((TokenKind::Question, 1).into());
-                lexer.advance();
-            }
-            Some('+') => {
-                lexer.add_token((TokenKind::Plus, 1).into());
-                lexer.advance();
-            }
-            Some('-') => {
-                lexer.add_token((TokenKind::Minus, 1).into());
-                lexer.advance();
-            }
-            Some('^') => {
-                lexer.add_token((TokenKind::Caret, 1).into());
-                lexer.advance();
-            }
-            Some('|') => {
-                lexer.add_token((TokenKind::Pipe, 1).into());
-                lexer.advance();
-            }
-            Some('&') => {
-                lexer.add_token((TokenKind::And, 1).into());
-                lexer.advance();
-            }
-            Some('%') => {
-                lexer.add_token((TokenKind::Percent, 1).into());
                 lexer.advance();
-            }
-            token => {
-                // Unknown or identifier and type
-                if lexer.len > lexer.i {
-                    other::lex_number(&mut lexer);
-                    strings::lex_string(&mut lexer);
-                    other::lex_type(&mut lexer);
-                    other::lex_unknown_or_ident(&mut lexer);
-                } else {
-                    panic!(
-                        "unknown token at index [{}]: {:?}, before it: {:?}",
-                        lexer.i,
-                        token,
-                        lexer.src.get(lexer.i - 1)
-                    );
-                }
+
+                let span = lexer.span_from(start);
+                lexer.error(
+                    Diagnostic::error(format!("unknown character {:?}", c))
+                        .with_code(ErrorCode::UnknownCharacter)
+                        .with_label(span, "not valid here"),
+                );
             }
         }
+        // The end of the file was checked above.
+        None => {}
     }
+}
+
+/// Turns `src` into tokens. Errors are added to `diagnostics` and the bad input is skipped,
+/// so the returned tokens can still be parsed.
+///
+/// Use a [`Lexer`] to get the tokens one at a time instead.
+pub fn lexer<'src>(
+    src: &'src str,
+    file: FileId,
+    diagnostics: &mut Diagnostics,
+) -> Vec<Token<'src>> {
+    let mut tokens = Vec::new();
 
-    lexer.add_token(Token {
-        kind: TokenKind::Eof,
-        size: 0,
-    });
+    for result in Lexer::new(src, file) {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(e) => diagnostics.push(e),
+        }
+    }
 
-    lexer.tokens
+    tokens
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::diagnostics::Diagnostics;
     use crate::lexer::*;
+    use crate::span::{FileId, Span};
 
     #[test]
     fn test_lexer() {
         let code = "fn main() {}";
 
-        let tokens = lexer(code);
+        let tokens = lexer(code, FileId(0), &mut Diagnostics::new());
 
         let a = format!("{:?}", tokens);
         let b = format!(
@@ -411,31 +650,31 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::Fn,
-                    size: 3
+                    span: Span::new(FileId(0), 0, 2, 1, 1)
                 },
                 Token {
-                    kind: TokenKind::Unknown("main".to_string()),
-                    size: 4
+                    kind: TokenKind::Unknown("main".into()),
+                    span: Span::new(FileId(0), 3, 4, 1, 4)
                 },
                 Token {
                     kind: TokenKind::LeftParen,
-                    size: 1
+                    span: Span::new(FileId(0), 7, 1, 1, 8)
                 },
                 Token {
                     kind: TokenKind::RightParen,
-                    size: 1
+                    span: Span::new(FileId(0), 8, 1, 1, 9)
                 },
                 Token {
                     kind: TokenKind::LeftBracket,
-                    size: 1
+                    span: Span::new(FileId(0), 10, 1, 1, 11)
                 },
                 Token {
                     kind: TokenKind::RightBracket,
-                    size: 1
+                    span: Span::new(FileId(0), 11, 1, 1, 12)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 12, 0, 1, 13)
                 }
             ]
         );
@@ -443,15 +682,174 @@ mod tests {
         assert_eq!(a, b);
     }
     #[test]
+    fn test_lexer_spans_over_multiple_lines() {
+        let code = "fn main() {\n    foo;\n}";
+
+        let spans = lexer(code, FileId(3), &mut Diagnostics::new())
+            .into_iter()
+            .map(|t| t.span)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans[4],
+            Span::new(FileId(3), 10, 1, 1, 11),
+            "Test case 1 failed"
+        );
+        assert_eq!(
+            spans[5],
+            Span::new(FileId(3), 16, 3, 2, 5),
+            "Test case 2 failed"
+        );
+        assert_eq!(
+            spans[6],
+            Span::new(FileId(3), 19, 1, 2, 8),
+            "Test case 3 failed"
+        );
+        assert_eq!(
+            spans[7],
+            Span::new(FileId(3), 21, 1, 3, 1),
+            "Test case 4 failed"
+        );
+        assert_eq!(
+            spans[8],
+            Span::new(FileId(3), 22, 0, 3, 2),
+            "Test case 5 failed"
+        );
+    }
+    #[test]
+    fn test_lexer_reports_errors_and_keeps_going() {
+        let code = "a @ b # 99999999999999999999 ";
+        let mut diagnostics = Diagnostics::new();
+
+        let kinds = lexer(code, FileId(0), &mut diagnostics)
+            .into_iter()
+            .map(|t| t.kind)
+            .collect::<Vec<_>>();
+        let codes = diagnostics.iter().map(|d| d.code).collect::<Vec<_>>();
+
+        assert_eq!(
+            format!("{:?}", kinds),
+            "[Unknown(\"a\"), Unknown(\"b\"), IntegerLiteral(0, None), Eof]"
+        );
+        assert_eq!(
+            codes,
+            vec![
+                Some(ErrorCode::UnknownCharacter),
+                Some(ErrorCode::UnknownCharacter),
+                Some(ErrorCode::IntegerOverflow)
+            ]
+        );
+        assert_eq!(
+            diagnostics.iter().next().unwrap().primary_span(),
+            Some(Span::new(FileId(0), 2, 1, 1, 3))
+        );
+    }
+    #[test]
+    fn test_lexer_with_non_ascii_text() {
+        let code = "// héllo wörld 🌍\n/* ünïcode */ a € b";
+        let mut diagnostics = Diagnostics::new();
+
+        let tokens = lexer(code, FileId(0), &mut diagnostics);
+        let errors = diagnostics.iter().collect::<Vec<_>>();
+
+        assert_eq!(
+            format!("{:?}", tokens),
+            format!(
+                "{:?}",
+                vec![
+                    Token {
+                        kind: TokenKind::Unknown("a".into()),
+                        span: Span::new(FileId(0), 38, 1, 2, 15)
+                    },
+                    Token {
+                        kind: TokenKind::Unknown("b".into()),
+                        span: Span::new(FileId(0), 44, 1, 2, 19)
+                    },
+                    Token {
+                        kind: TokenKind::Eof,
+                        span: Span::new(FileId(0), 45, 0, 2, 20)
+                    }
+                ]
+            )
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unknown character '€'");
+        assert_eq!(
+            errors[0].primary_span(),
+            Some(Span::new(FileId(0), 40, 3, 2, 17))
+        );
+    }
+    #[test]
+    fn test_lexer_iterator() {
+        let mut lexer = Lexer::new("a @ 1;", FileId(0));
+
+        assert_eq!(
+            lexer.peek().map(|r| r.as_ref().map(|t| t.kind.clone())),
+            Some(Ok(TokenKind::Unknown("a".into())))
+        );
+        // Errors take a place in the lookahead too.
+        assert!(matches!(lexer.peek_nth(1), Some(Err(_))));
+        assert_eq!(
+            lexer.peek_nth(3).map(|r| r.as_ref().map(|t| t.span)),
+            Some(Ok(Span::new(FileId(0), 5, 1, 1, 6)))
+        );
+
+        let items = lexer
+            .map(|r| r.map(|t| t.kind.to_string()).map_err(|e| e.message))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            items,
+            vec![
+                Ok("identifier `a`".to_string()),
+                Err("unknown character '@'".to_string()),
+                Ok("integer `1`".to_string()),
+                Ok("`;`".to_string()),
+                Ok("end of file".to_string()),
+            ]
+        );
+    }
+    #[test]
+    fn test_lexer_only_lexes_what_is_asked_for() {
+        let mut lexer = Lexer::new("fn main() { \"never lexed", FileId(0));
+
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Fn);
+        assert_eq!(
+            lexer.peek_nth(2).unwrap().as_ref().unwrap().kind,
+            TokenKind::RightParen
+        );
+        // The unterminated string hasn't been reached.
+        assert_eq!(lexer.queue.len(), 3);
+        assert!(!lexer.done);
+    }
+    #[test]
+    fn test_lexer_ends_after_eof() {
+        let mut lexer = Lexer::new("", FileId(0));
+
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Eof);
+        assert!(lexer.next().is_none());
+        assert!(lexer.peek().is_none());
+        assert!(lexer.next().is_none());
+    }
+    #[test]
+    fn test_lexer_skips_byte_order_mark() {
+        let mut lexer = Lexer::new("\u{feff}fn", FileId(0));
+
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.kind, TokenKind::Fn);
+        assert_eq!(token.span, Span::new(FileId(0), 3, 2, 1, 1));
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Eof);
+    }
+    #[test]
     fn test_partialeq_impl_for_tokenkind_with_unknown() {
         let token = Token {
-            kind: TokenKind::Unknown("main".to_string()),
-            size: 3,
+            kind: TokenKind::Unknown("main".into()),
+            span: Span::default(),
         };
 
         // This returns true, which is expected behaviour.
         // I know it's weird, but I was lazy. Make it better if it bothers you.
         // Warning: You have to fix ~200 lines of code that rely on that as well.
-        assert_eq!(token.kind, TokenKind::Unknown("".to_string()))
+        assert_eq!(token.kind, TokenKind::Unknown("".into()))
     }
 }
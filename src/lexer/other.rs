@@ -1,116 +1,243 @@
 use super::*;
 
-/// A range where there is either an identifier or a syntax error.
+/// A range where there is either an identifier, a keyword or a syntax error.
+/// The whole word is read first, then it's checked against the keyword table.
+///
+/// Identifiers follow UAX #31: they start with `_` or a letter of any script and go on with
+/// letters, digits and combining marks. They are normalized to NFC.
 pub fn lex_unknown_or_ident(l: &mut Lexer) {
-    let mut res = String::new();
-    let start = l.i;
-    let mut end = l.i;
-
-    while l.len > l.i {
-        match l.src[l.i] {
-            'a'..='z' => {
-                end += 1;
-                res.push(l.src[l.i])
-            }
-            'A'..='Z' => {
-                end += 1;
-                res.push(l.src[l.i])
-            }
-            '0'..='9' => {
-                end += 1;
-                res.push(l.src[l.i])
-            }
-            '_' => {
-                end += 1;
-                res.push(l.src[l.i])
-            }
+    let start = l.here();
+
+    if let Some(0x80..) = l.byte() {
+        if !l.current_char().is_some_and(unicode::is_ident_start) {
+            return;
+        }
+    }
+
+    loop {
+        match l.byte() {
+            Some(b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_') => {}
+            Some(0x80..) if l.current_char().is_some_and(unicode::is_ident_continue) => {}
             _ => break,
         }
 
         l.advance();
     }
 
-    if start == end {
+    let word = &l.src[start.offset..l.i];
+
+    if word.is_empty() {
         return;
     }
 
-    l.add_token(Token {
-        kind: TokenKind::Unknown(res),
-        size: (end - start),
-    });
+    let span = l.span_from(start);
+    let word = unicode::normalize(word);
+
+    let kind = match keywords::keyword(&word) {
+        Some(kind) => kind,
+        None => {
+            unicode::lint_identifier(l, word.clone(), span);
+            TokenKind::Unknown(word)
+        }
+    };
+
+    l.add_token(Token { kind, span });
 }
 
-pub fn lex_type(l: &mut Lexer) {
-    // void
-    if l.src.get(l.i) == Some('v').as_ref()
-        && l.src.get(l.i + 1) == Some('o').as_ref()
-        && l.src.get(l.i + 2) == Some('i').as_ref()
-        && l.src.get(l.i + 3) == Some('d').as_ref()
-    {
-        l.advance_with(4);
+/// Reads digits of `radix` and `_`, the underscores are left out of the result.
+fn lex_digits(l: &mut Lexer, radix: u32) -> String {
+    let mut res = String::new();
 
-        l.add_token(Token {
-            kind: TokenKind::Type(Type::Void),
-            size: 4,
-        });
+    while let Some(c) = l.byte() {
+        if (c as char).is_digit(radix) {
+            res.push(c as char);
+        } else if c != b'_' {
+            break;
+        }
 
-        return;
+        l.advance();
     }
 
-    // i32
-    if l.src.get(l.i) == Some('i').as_ref()
-        && l.src.get(l.i + 1) == Some('3').as_ref()
-        && l.src.get(l.i + 2) == Some('2').as_ref()
-    {
-        l.advance_with(3);
-
-        l.add_token(Token {
-            kind: TokenKind::Type(Type::I32),
-            size: 3,
-        });
+    res
+}
 
+/// Turns a number into tokens.
+///
+/// Integers can be written in decimal, hex (`0xFF`), octal (`0o755`) or binary (`0b1010`), and
+/// have a type suffix (`10u8`). Floats are decimal with a fraction, an exponent or both
+/// (`1.5`, `1e-9`). Digits can be separated with `_`.
+pub fn lex_number(l: &mut Lexer) {
+    if !matches!(l.byte(), Some(b'0'..=b'9')) {
         return;
     }
 
-    // u32
-    if l.src.get(l.i) == Some('u').as_ref()
-        && l.src.get(l.i + 1) == Some('3').as_ref()
-        && l.src.get(l.i + 2) == Some('2').as_ref()
-    {
-        l.advance_with(3);
+    let start = l.here();
 
-        l.add_token(Token {
-            kind: TokenKind::Type(Type::U32),
-            size: 3,
-        });
+    let (radix, base) = match (l.byte(), l.byte_at(1)) {
+        (Some(b'0'), Some(b'x')) => (16, "hexadecimal"),
+        (Some(b'0'), Some(b'o')) => (8, "octal"),
+        (Some(b'0'), Some(b'b')) => (2, "binary"),
+        _ => (10, "decimal"),
+    };
+
+    if radix != 10 {
+        l.advance_with(2);
     }
-}
 
-/// Turns a number into tokens. Doesn't support floating point numbers.
-pub fn lex_number(l: &mut Lexer) {
-    let mut res = String::new();
-    let start = l.i;
-    let mut end = l.i;
-
-    while l.len > l.i {
-        match l.src[l.i] {
-            '0'..='9' => {
-                end += 1;
-                res.push(l.src[l.i])
+    let mut digits = lex_digits(l, radix);
+    let mut float = false;
+
+    if radix == 10 {
+        // Fraction. `1..2` is a range and `1.foo` a field or a method, not floats.
+        if l.byte() == Some(b'.') && matches!(l.byte_at(1), Some(b'0'..=b'9')) {
+            float = true;
+            digits.push('.');
+            l.advance();
+            digits += &lex_digits(l, 10);
+        }
+
+        // Exponent
+        if matches!(l.byte(), Some(b'e' | b'E')) {
+            let sign = matches!(l.byte_at(1), Some(b'+' | b'-')) as usize;
+
+            if matches!(l.byte_at(1 + sign), Some(b'0'..=b'9')) {
+                float = true;
+                digits.push('e');
+                l.advance();
+
+                if sign == 1 {
+                    digits.push(l.byte().unwrap() as char);
+                    l.advance();
+                }
+
+                digits += &lex_digits(l, 10);
             }
-            _ => break,
+        }
+    }
+
+    // Whatever is left of the word is the suffix.
+    let suffix_start = l.here();
+
+    while let Some(c) = l.byte() {
+        if !c.is_ascii_alphanumeric() && c != b'_' {
+            break;
         }
 
         l.advance();
     }
 
-    if res.is_empty() {
+    let suffix = &l.src[suffix_start.offset..l.i];
+
+    let span = l.span_from(start);
+    let suffix_span = l.span_from(suffix_start);
+
+    // `0o8` only gets the "invalid digit" error below.
+    if digits.is_empty() && !suffix.starts_with(|c: char| c.is_ascii_digit()) {
+        l.error(
+            Diagnostic::error(format!("no valid digits found for {} number", base))
+                .with_code(ErrorCode::InvalidNumber)
+                .with_label(span, "expected digits"),
+        );
+    }
+
+    let ty = match suffix {
+        "" => Type::None,
+        "i32" if !float => Type::I32,
+        "u32" if !float => Type::U32,
+        "u8" if !float => Type::U8,
+        _ if suffix.starts_with(|c: char| c.is_ascii_digit()) => {
+            l.error(
+                Diagnostic::error(format!(
+                    "invalid digit `{}` in {} number",
+                    suffix.chars().next().unwrap(),
+                    base
+                ))
+                .with_code(ErrorCode::InvalidNumber)
+                .with_label(suffix_span, "not a valid digit"),
+            );
+
+            Type::None
+        }
+        _ => {
+            l.error(
+                Diagnostic::error(format!(
+                    "invalid suffix `{}` for {} literal",
+                    suffix,
+                    if float { "float" } else { "number" }
+                ))
+                .with_code(ErrorCode::InvalidNumber)
+                .with_label(suffix_span, "invalid suffix")
+                .with_note(if float {
+                    "float literals can't have a suffix"
+                } else {
+                    "the valid suffixes are `i32`, `u32` and `u8`"
+                }),
+            );
+
+            Type::None
+        }
+    };
+
+    if float {
+        // Only digits, a `.` and an exponent are in there, so it always parses.
+        let mut value: f64 = digits.parse().unwrap_or(0.0);
+
+        if !value.is_finite() {
+            l.error(
+                Diagnostic::error("float literal is too large")
+                    .with_code(ErrorCode::IntegerOverflow)
+                    .with_label(span, format!("the largest float is {:e}", f64::MAX)),
+            );
+
+            value = 0.0;
+        }
+
+        l.add_token(Token {
+            kind: TokenKind::FloatLiteral(value),
+            span,
+        });
+
         return;
     }
 
+    let max = match ty {
+        Type::I32 => i32::MAX as u64,
+        Type::U32 => u32::MAX as u64,
+        Type::U8 => u8::MAX as u64,
+        _ => i64::MAX as u64,
+    };
+    // The negative ones go one further.
+    let limit = match ty {
+        Type::I32 | Type::None => max + 1,
+        _ => max,
+    };
+
+    let value = match u64::from_str_radix(&digits, radix) {
+        Ok(value) if value <= limit => value,
+        Ok(_) if ty != Type::None => {
+            l.error(
+                Diagnostic::error(format!("literal out of range for `{}`", ty))
+                    .with_code(ErrorCode::IntegerOverflow)
+                    .with_label(span, format!("the largest `{}` is {}", ty, max)),
+            );
+
+            0
+        }
+        Err(_) if digits.is_empty() => 0,
+        _ => {
+            l.error(
+                Diagnostic::error("integer literal is too large")
+                    .with_code(ErrorCode::IntegerOverflow)
+                    .with_label(span, format!("the largest integer is {}", i64::MAX)),
+            );
+
+            0
+        }
+    };
+
     l.add_token(Token {
-        kind: TokenKind::IntegerLiteral(res.parse().expect("idk")),
-        size: end - start,
+        kind: TokenKind::IntegerLiteral(value, ty),
+        span,
     });
 }
 
@@ -120,65 +247,247 @@ mod tests {
 
     #[test]
     fn test_type_void() {
-        let src = " void2";
-        let mut lexer = Lexer::new(src);
+        let src = " void;";
+        let mut lexer = Lexer::new(src, FileId(0));
 
         lexer.advance();
 
-        lex_type(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Type(Type::Void),
-                size: 4
+                span: Span::new(FileId(0), 1, 4, 1, 2)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '2');
+        assert_eq!(lexer.byte(), Some(b';'));
     }
     #[test]
     fn test_type_i32() {
-        let src = " i320";
-        let mut lexer = Lexer::new(src);
+        let src = " i32,";
+        let mut lexer = Lexer::new(src, FileId(0));
 
         lexer.advance();
 
-        lex_type(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Type(Type::I32),
-                size: 3
+                span: Span::new(FileId(0), 1, 3, 1, 2)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '0');
+        assert_eq!(lexer.byte(), Some(b','));
     }
     #[test]
     fn test_type_u32() {
-        let src = " u320";
-        let mut lexer = Lexer::new(src);
+        let src = " u32)";
+        let mut lexer = Lexer::new(src, FileId(0));
 
         lexer.advance();
 
-        lex_type(&mut lexer);
+        lex_unknown_or_ident(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::Type(Type::U32),
-                size: 3
+                span: Span::new(FileId(0), 1, 3, 1, 2)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '0');
+        assert_eq!(lexer.byte(), Some(b')'));
+    }
+    #[test]
+    fn test_type_u8_and_string() {
+        let tokens = lexer("u8 string u8x 1u8", FileId(0), &mut Diagnostics::new());
+
+        assert_eq!(
+            format!(
+                "{:?}",
+                tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+            ),
+            format!(
+                "{:?}",
+                vec![
+                    TokenKind::Type(Type::U8),
+                    TokenKind::Type(Type::String),
+                    TokenKind::Unknown("u8x".into()),
+                    TokenKind::IntegerLiteral(1, Type::U8),
+                    TokenKind::Eof
+                ]
+            )
+        );
+    }
+    #[test]
+    fn test_type_is_a_whole_word() {
+        let src = " i320";
+        let mut lexer = Lexer::new(src, FileId(0));
+
+        lexer.advance();
+
+        lex_unknown_or_ident(&mut lexer);
+
+        let a = format!("{:?}", lexer.next().unwrap().unwrap().kind);
+        let b = format!("{:?}", TokenKind::Unknown("i320".into()));
+
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn test_lex_number() {
+        let cases = [
+            ("123", TokenKind::IntegerLiteral(123, Type::None)),
+            (
+                "1_000_000",
+                TokenKind::IntegerLiteral(1_000_000, Type::None),
+            ),
+            ("0xFF", TokenKind::IntegerLiteral(0xFF, Type::None)),
+            (
+                "0xdead_beef",
+                TokenKind::IntegerLiteral(0xdead_beef, Type::None),
+            ),
+            ("0o755", TokenKind::IntegerLiteral(0o755, Type::None)),
+            ("0b1010", TokenKind::IntegerLiteral(0b1010, Type::None)),
+            ("10u8", TokenKind::IntegerLiteral(10, Type::U8)),
+            ("0xFFu32", TokenKind::IntegerLiteral(0xFF, Type::U32)),
+            ("7_i32", TokenKind::IntegerLiteral(7, Type::I32)),
+            // One too large, the parser only allows them after a `-`.
+            (
+                "9223372036854775808",
+                TokenKind::IntegerLiteral(9223372036854775808, Type::None),
+            ),
+            (
+                "2147483648i32",
+                TokenKind::IntegerLiteral(2147483648, Type::I32),
+            ),
+            ("1.5", TokenKind::FloatLiteral(1.5)),
+            ("1e-9", TokenKind::FloatLiteral(1e-9)),
+            ("2.5E+3", TokenKind::FloatLiteral(2.5E+3)),
+            ("1_000.000_1", TokenKind::FloatLiteral(1_000.000_1)),
+        ];
+
+        for (i, (code, expected)) in cases.iter().enumerate() {
+            let mut d = Diagnostics::new();
+            let tokens = lexer(code, FileId(0), &mut d);
+
+            assert!(d.is_empty(), "Test case {} failed", i + 1);
+            assert_eq!(
+                format!("{:?}", tokens[0]),
+                format!(
+                    "{:?}",
+                    Token {
+                        kind: expected.clone(),
+                        span: Span::new(FileId(0), 0, code.len(), 1, 1)
+                    }
+                ),
+                "Test case {} failed",
+                i + 1
+            );
+        }
+    }
+    #[test]
+    fn test_lex_number_ranges_and_fields() {
+        let tokens = lexer("0..10 1.foo", FileId(0), &mut Diagnostics::new());
+
+        assert_eq!(
+            format!(
+                "{:?}",
+                tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+            ),
+            format!(
+                "{:?}",
+                vec![
+                    TokenKind::IntegerLiteral(0, Type::None),
+                    TokenKind::DoubleDot,
+                    TokenKind::IntegerLiteral(10, Type::None),
+                    TokenKind::IntegerLiteral(1, Type::None),
+                    TokenKind::Dot,
+                    TokenKind::Unknown("foo".into()),
+                    TokenKind::Eof
+                ]
+            )
+        );
+    }
+    #[test]
+    fn test_lex_number_errors() {
+        let cases = [
+            (
+                "0x",
+                ErrorCode::InvalidNumber,
+                "no valid digits found for hexadecimal number",
+            ),
+            (
+                "0b102",
+                ErrorCode::InvalidNumber,
+                "invalid digit `2` in binary number",
+            ),
+            (
+                "0o8",
+                ErrorCode::InvalidNumber,
+                "invalid digit `8` in octal number",
+            ),
+            (
+                "12abc",
+                ErrorCode::InvalidNumber,
+                "invalid suffix `abc` for number literal",
+            ),
+            (
+                "1.5u8",
+                ErrorCode::InvalidNumber,
+                "invalid suffix `u8` for float literal",
+            ),
+            (
+                "256u8",
+                ErrorCode::IntegerOverflow,
+                "literal out of range for `u8`",
+            ),
+            (
+                "0x1_0000_0000u32",
+                ErrorCode::IntegerOverflow,
+                "literal out of range for `u32`",
+            ),
+            (
+                "0xFFFF_FFFF_FFFF_FFFF",
+                ErrorCode::IntegerOverflow,
+                "integer literal is too large",
+            ),
+            (
+                "1e999",
+                ErrorCode::IntegerOverflow,
+                "float literal is too large",
+            ),
+            (
+                "9223372036854775809",
+                ErrorCode::IntegerOverflow,
+                "integer literal is too large",
+            ),
+            (
+                "2147483649i32",
+                ErrorCode::IntegerOverflow,
+                "literal out of range for `i32`",
+            ),
+        ];
+
+        for (i, (code, error, message)) in cases.iter().enumerate() {
+            let mut d = Diagnostics::new();
+            let tokens = lexer(code, FileId(0), &mut d);
+            let errors = d.iter().collect::<Vec<_>>();
+
+            assert_eq!(errors.len(), 1, "Test case {} failed", i + 1);
+            assert_eq!(errors[0].code, Some(*error), "Test case {} failed", i + 1);
+            assert_eq!(errors[0].message, *message, "Test case {} failed", i + 1);
+            // There is still a number for the parser, and only one.
+            assert_eq!(tokens.len(), 2, "Test case {} failed", i + 1);
+        }
     }
 }
@@ -1,107 +1,58 @@
 use super::{Lexer, Token, TokenKind};
 
+/// Every sequence of more than one character that is a token. Longer ones have to come before
+/// the ones they start with, so `<<=` isn't lexed as `<<` and `=`.
+pub const SEQUENCES: &[(&str, TokenKind<'static>)] = &[
+    ("<<=", TokenKind::ShiftLeftEq),
+    (">>=", TokenKind::ShiftRightEq),
+    ("..=", TokenKind::DoubleDotEq),
+    ("==", TokenKind::DoubleEquals),
+    ("!=", TokenKind::BangEquals),
+    (">=", TokenKind::GtEq),
+    ("<=", TokenKind::LtEq),
+    ("::", TokenKind::DoubleColon),
+    ("..", TokenKind::DoubleDot),
+    ("->", TokenKind::Arrow),
+    ("=>", TokenKind::FatArrow),
+    ("||", TokenKind::DoublePipe),
+    ("&&", TokenKind::DoubleAnd),
+    ("<<", TokenKind::ShiftLeft),
+    (">>", TokenKind::ShiftRight),
+    ("**", TokenKind::DoubleStar),
+    ("++", TokenKind::DoublePlus),
+    ("--", TokenKind::DoubleMinus),
+    ("+=", TokenKind::PlusEq),
+    ("-=", TokenKind::MinusEq),
+    ("*=", TokenKind::StarEq),
+    ("/=", TokenKind::SlashEq),
+    ("%=", TokenKind::PercentEq),
+    ("&=", TokenKind::AndEq),
+    ("|=", TokenKind::PipeEq),
+    ("^=", TokenKind::CaretEq),
+];
+
 /// Checks for sequences like "::" and "=>".
 pub fn lex_sequence(l: &mut Lexer) -> bool {
-    // "=="
-    if l.src.get(l.i) == Some('=').as_ref() && l.src.get(l.i + 1) == Some('=').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::DoubleEquals,
-            size: 2,
-        });
-
-        return true;
-    }
-
-    // "!="
-    if l.src.get(l.i) == Some('!').as_ref() && l.src.get(l.i + 1) == Some('=').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::BangEquals,
-            size: 2,
-        });
-
-        return true;
-    }
-
-    // ">="
-    if l.src.get(l.i) == Some('>').as_ref() && l.src.get(l.i + 1) == Some('=').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::GtEq,
-            size: 2,
-        });
-
-        return true;
-    }
-
-    // "<="
-    if l.src.get(l.i) == Some('<').as_ref() && l.src.get(l.i + 1) == Some('=').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::LtEq,
-            size: 2,
-        });
-
-        return true;
-    }
-
-    // "::"
-    if l.src.get(l.i) == Some(':').as_ref() && l.src.get(l.i + 1) == Some(':').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::DoubleColon,
-            size: 2,
-        });
+    let rest = l.rest();
+    let found = SEQUENCES.iter().find(|(seq, _)| rest.starts_with(seq));
 
-        return true;
-    }
-
-    // ".."
-    if l.src.get(l.i) == Some('.').as_ref() && l.src.get(l.i + 1) == Some('.').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::DoubleDot,
-            size: 2,
-        });
-
-        return true;
-    }
-
-    // "->"
-    if l.src.get(l.i) == Some('-').as_ref() && l.src.get(l.i + 1) == Some('>').as_ref() {
-        l.advance_with(2);
-
-        l.add_token(Token {
-            kind: TokenKind::Arrow,
-            size: 2,
-        });
-
-        return true;
-    }
+    let (seq, kind) = match found {
+        Some(a) => a,
+        None => return false,
+    };
 
-    // "=>"
-    if l.src.get(l.i) == Some('=').as_ref() && l.src.get(l.i + 1) == Some('>').as_ref() {
-        l.advance_with(2);
+    let start = l.here();
 
-        l.add_token(Token {
-            kind: TokenKind::FatArrow,
-            size: 2,
-        });
+    l.advance_with(seq.len());
 
-        return true;
-    }
+    l.add_token(Token {
+        kind: kind.clone(),
+        span: l.span_from(start),
+    });
 
-    false
+    true
 }
 
-// TODO: write test for every sequence
 #[cfg(test)]
 mod tests {
     use super::super::*;
@@ -110,44 +61,117 @@ mod tests {
     #[test]
     fn test_sequence_double_colon() {
         let src = "a::b";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
         lexer.advance();
 
         lex_sequence(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::DoubleColon,
-                size: 2
+                span: Span::new(FileId(0), 1, 2, 1, 2)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], 'b');
+        assert_eq!(lexer.byte(), Some(b'b'));
     }
 
     #[test]
     fn test_sequence_double_dot() {
         let src = "0..23";
-        let mut lexer = Lexer::new(src);
+        let mut lexer = Lexer::new(src, FileId(0));
 
         lexer.advance();
 
         lex_sequence(&mut lexer);
 
-        let a = format!("{:?}", lexer.tokens.last().unwrap());
+        let a = format!("{:?}", lexer.next().unwrap().unwrap());
         let b = format!(
             "{:?}",
             Token {
                 kind: TokenKind::DoubleDot,
-                size: 2
+                span: Span::new(FileId(0), 1, 2, 1, 2)
             }
         );
 
         assert_eq!(a, b);
-        assert_eq!(lexer.src[lexer.i], '2');
+        assert_eq!(lexer.byte(), Some(b'2'));
+    }
+    #[test]
+    fn test_every_sequence() {
+        for (seq, kind) in SEQUENCES {
+            let src = format!("a{}b", seq);
+            let tokens = lexer(&src, FileId(0), &mut Diagnostics::new());
+
+            let a = format!("{:?}", tokens[1]);
+            let b = format!(
+                "{:?}",
+                Token {
+                    kind: kind.clone(),
+                    span: Span::new(FileId(0), 1, seq.len(), 1, 2)
+                }
+            );
+
+            assert_eq!(a, b, "Test case {} failed", seq);
+            assert_eq!(tokens.len(), 4, "Test case {} failed", seq);
+        }
+    }
+    #[test]
+    fn test_sequences_prefer_the_longest() {
+        let tokens = lexer("a<<=b<<c<d ~e ...", FileId(0), &mut Diagnostics::new());
+
+        assert_eq!(
+            format!(
+                "{:?}",
+                tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+            ),
+            format!(
+                "{:?}",
+                vec![
+                    TokenKind::Unknown("a".into()),
+                    TokenKind::ShiftLeftEq,
+                    TokenKind::Unknown("b".into()),
+                    TokenKind::ShiftLeft,
+                    TokenKind::Unknown("c".into()),
+                    TokenKind::LessThan,
+                    TokenKind::Unknown("d".into()),
+                    TokenKind::Tilde,
+                    TokenKind::Unknown("e".into()),
+                    TokenKind::DoubleDot,
+                    TokenKind::Dot,
+                    TokenKind::Eof
+                ]
+            )
+        );
+    }
+    #[test]
+    fn test_lex_shift_and_power() {
+        let tokens = lexer("a>>b**-c***d", FileId(0), &mut Diagnostics::new());
+
+        assert_eq!(
+            format!(
+                "{:?}",
+                tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+            ),
+            format!(
+                "{:?}",
+                vec![
+                    TokenKind::Unknown("a".into()),
+                    TokenKind::ShiftRight,
+                    TokenKind::Unknown("b".into()),
+                    TokenKind::DoubleStar,
+                    TokenKind::Minus,
+                    TokenKind::Unknown("c".into()),
+                    TokenKind::DoubleStar,
+                    TokenKind::Star,
+                    TokenKind::Unknown("d".into()),
+                    TokenKind::Eof
+                ]
+            )
+        );
     }
 }
@@ -1,89 +1,380 @@
+use std::borrow::Cow;
+
 use super::*;
 
+/// Used in place of characters that couldn't be lexed, the error has already been reported.
+pub const REPLACEMENT: char = '\u{FFFD}';
+
 /// Lex a string literal.
+///
+/// A `\` at the end of a line joins it with the next one, the line break and the whitespace
+/// at the start of the next line are skipped.
+///
+/// The string is borrowed from the source, unless it has escapes or line continuations in it.
 pub fn lex_string(l: &mut Lexer) {
-    if l.src.get(l.i) == Some('"').as_ref() {
-        let mut res = String::new();
-        let mut ctr = 1;
+    if lex_raw_string(l) {
+        return;
+    }
+
+    if l.byte() == Some(b'"') {
+        let start = l.here();
 
         l.advance();
 
-        while l.len > l.i {
-            if l.src[l.i] == '"' {
-                l.advance();
-                ctr += 1;
-                break;
+        let quote = l.span_from(start);
+        // Only allocated once something has to be changed. `run` is where the part that
+        // hasn't been copied into it yet starts.
+        let mut owned: Option<String> = None;
+        let mut run = l.i;
+
+        let end = loop {
+            match l.byte() {
+                Some(b'"') => {
+                    let end = l.i;
+                    l.advance();
+                    break end;
+                }
+                Some(b'\\') if matches!(l.byte_at(1), Some(b'\n' | b'\r')) => {
+                    owned
+                        .get_or_insert_with(String::new)
+                        .push_str(&l.src[run..l.i]);
+
+                    l.advance();
+                    l.skip_whitespace();
+
+                    run = l.i;
+                }
+                Some(b'\\') => {
+                    owned
+                        .get_or_insert_with(String::new)
+                        .push_str(&l.src[run..l.i]);
+
+                    let c = lex_escape(l);
+                    owned.get_or_insert_with(String::new).push(c);
+
+                    run = l.i;
+                }
+                Some(_) => l.advance(),
+                None => {
+                    let here = l.here();
+                    l.error(
+                        Diagnostic::error("unterminated string literal")
+                            .with_code(ErrorCode::UnterminatedString)
+                            .with_label(quote, "string literal starts here")
+                            .with_secondary(here, "the file ends here"),
+                    );
+
+                    break l.i;
+                }
             }
+        };
 
-            if l.src.get(l.i) == Some('\\').as_ref() && l.src.get(l.i + 1) == Some('"').as_ref() {
-                l.advance_with(2);
-                ctr += 2;
-                res.push('"');
-            } else if l.src.get(l.i).is_none() || l.src.get(l.i + 1).is_none() {
-                break;
-            } else {
-                res.push(l.src[l.i]);
-                l.advance();
-                ctr += 1;
+        let data = match owned {
+            Some(mut res) => {
+                res.push_str(&l.src[run..end]);
+                Cow::Owned(res)
             }
-        }
+            None => Cow::Borrowed(&l.src[run..end]),
+        };
 
         l.add_token(Token {
-            kind: TokenKind::StringLiteral(res),
-            size: ctr,
+            kind: TokenKind::StringLiteral(data),
+            span: l.span_from(start),
         });
     }
 }
 
-/// Lex a character literal.
-pub fn lex_char(l: &mut Lexer) -> bool {
-    if l.src[l.i] == '\'' {
-        let res;
-        let mut ctr = 1;
+/// Lex a raw string literal, `r"..."` or `r#"..."#`. Nothing is escaped in them, and as many
+/// `#` as needed can be used so the string can contain `"#`. Returns false if there isn't one.
+fn lex_raw_string(l: &mut Lexer) -> bool {
+    if l.byte() != Some(b'r') {
+        return false;
+    }
+
+    let hashes = l.rest()[1..].bytes().take_while(|c| *c == b'#').count();
+
+    if l.byte_at(1 + hashes) != Some(b'"') {
+        return false;
+    }
 
+    let start = l.here();
+
+    for _ in 0..hashes + 2 {
         l.advance();
+    }
+
+    let opening = l.span_from(start);
+    let begin = l.i;
+
+    let end = loop {
+        match l.byte() {
+            Some(b'"')
+                if l.rest()[1..]
+                    .bytes()
+                    .take(hashes)
+                    .filter(|c| *c == b'#')
+                    .count()
+                    == hashes =>
+            {
+                let end = l.i;
+
+                for _ in 0..hashes + 1 {
+                    l.advance();
+                }
 
-        if l.src.get(l.i) == Some('\\').as_ref() && l.src.get(l.i + 1) == Some('\'').as_ref() {
-            res = '\'';
+                break end;
+            }
+            Some(_) => l.advance(),
+            None => {
+                let here = l.here();
+                l.error(
+                    Diagnostic::error("unterminated raw string literal")
+                        .with_code(ErrorCode::UnterminatedString)
+                        .with_label(opening, "raw string literal starts here")
+                        .with_secondary(here, "the file ends here")
+                        .with_note(format!(
+                            "raw string literals end with `\"{}`",
+                            "#".repeat(hashes)
+                        )),
+                );
+
+                break l.i;
+            }
+        }
+    };
 
-            ctr += 2;
-            l.advance_with(2);
-        } else if l.src.get(l.i) == Some('\\').as_ref() && l.src.get(l.i + 1) == Some('\\').as_ref()
-        {
-            res = '\\';
+    l.add_token(Token {
+        kind: TokenKind::StringLiteral(Cow::Borrowed(&l.src[begin..end])),
+        span: l.span_from(start),
+    });
 
-            ctr += 2;
-            l.advance_with(2);
-        } else {
-            res = l.src[l.i];
+    true
+}
 
-            ctr += 1;
-            l.advance();
+/// Lexes an escape sequence starting at the `\`. Invalid escapes are reported and
+/// REPLACEMENT is returned for them.
+///
+/// Supported escapes: `\n`, `\t`, `\r`, `\0`, `\\`, `\'`, `\"`, `\xNN` and `\u{NNNN}`.
+fn lex_escape(l: &mut Lexer) -> char {
+    let start = l.here();
+
+    l.advance();
+
+    let c = match l.current_char() {
+        Some(c) => c,
+        // The caller reports the missing quote.
+        None => return REPLACEMENT,
+    };
+
+    l.advance();
+
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' | '\'' | '"' => c,
+        'x' => lex_hex_escape(l, start),
+        'u' => lex_unicode_escape(l, start),
+        _ => {
+            let span = l.span_from(start);
+            l.error(
+                Diagnostic::error(format!("unknown character escape `\\{}`", c))
+                    .with_code(ErrorCode::InvalidEscape)
+                    .with_label(span, "unknown escape")
+                    .with_note(
+                        "valid escapes are \\n, \\t, \\r, \\0, \\\\, \\', \\\", \\xNN and \\u{NNNN}",
+                    ),
+            );
+
+            REPLACEMENT
+        }
+    }
+}
+
+/// Reads up to `max` hex digits.
+fn lex_hex_digits(l: &mut Lexer, max: usize) -> String {
+    let mut res = String::new();
+
+    while res.len() < max {
+        match l.byte() {
+            Some(c) if c.is_ascii_hexdigit() => res.push(c as char),
+            _ => break,
         }
 
-        println!("{:?}", l);
-        if l.src.get(l.i) == Some('\'').as_ref() {
-            ctr += 1;
-            l.advance();
+        l.advance();
+    }
+
+    res
+}
+
+/// `\xNN`, where NN is at most 7F. `start` is where the `\` is.
+fn lex_hex_escape(l: &mut Lexer, start: Span) -> char {
+    let digits = lex_hex_digits(l, 2);
+    let span = l.span_from(start);
+
+    if digits.len() != 2 {
+        l.error(
+            Diagnostic::error("numeric character escape is too short")
+                .with_code(ErrorCode::InvalidEscape)
+                .with_label(span, "expected two hex digits after `\\x`"),
+        );
+
+        return REPLACEMENT;
+    }
+
+    match u8::from_str_radix(&digits, 16) {
+        Ok(value) if value <= 0x7F => value as char,
+        _ => {
+            l.error(
+                Diagnostic::error("out of range hex escape")
+                    .with_code(ErrorCode::InvalidEscape)
+                    .with_label(span, "must be at most `\\x7F`")
+                    .with_note("use `\\u{NNNN}` for characters that aren't ASCII"),
+            );
+
+            REPLACEMENT
+        }
+    }
+}
+
+/// `\u{NNNN}` with one to six hex digits. `start` is where the `\` is.
+fn lex_unicode_escape(l: &mut Lexer, start: Span) -> char {
+    if l.byte() != Some(b'{') {
+        let span = l.span_from(start);
+        l.error(
+            Diagnostic::error("incorrect unicode escape sequence")
+                .with_code(ErrorCode::InvalidEscape)
+                .with_label(span, "expected `{` after `\\u`")
+                .with_note("unicode escapes are written like `\\u{1F600}`"),
+        );
+
+        return REPLACEMENT;
+    }
+
+    l.advance();
+
+    let digits = lex_hex_digits(l, 6);
+
+    if digits.is_empty() || l.byte() != Some(b'}') {
+        let span = l.span_from(start);
+        l.error(
+            Diagnostic::error("incorrect unicode escape sequence")
+                .with_code(ErrorCode::InvalidEscape)
+                .with_label(span, "expected one to six hex digits and a `}`")
+                .with_note("unicode escapes are written like `\\u{1F600}`"),
+        );
+
+        return REPLACEMENT;
+    }
+
+    l.advance();
+
+    let span = l.span_from(start);
+
+    // At most six digits, so it always fits.
+    let value = u32::from_str_radix(&digits, 16).unwrap();
+
+    match char::from_u32(value) {
+        Some(c) => c,
+        None => {
+            l.error(
+                Diagnostic::error("invalid unicode character escape")
+                    .with_code(ErrorCode::InvalidEscape)
+                    .with_label(span, "not a valid unicode character")
+                    .with_note("unicode escapes must be at most 10FFFF and not a surrogate"),
+            );
+
+            REPLACEMENT
+        }
+    }
+}
 
-            l.add_token(Token {
-                kind: TokenKind::CharLiteral(res),
-                size: ctr,
-            });
+/// Lex a character literal.
+pub fn lex_char(l: &mut Lexer) -> bool {
+    if l.byte() == Some(b'\'') {
+        let start = l.here();
 
-            return true;
+        l.advance();
+
+        let res = match l.current_char() {
+            Some('\\') => lex_escape(l),
+            Some(c) => {
+                l.advance();
+                c
+            }
+            None => {
+                let span = l.span_from(start);
+                l.error(
+                    Diagnostic::error("unterminated character literal")
+                        .with_code(ErrorCode::UnterminatedChar)
+                        .with_label(span, "the file ends here"),
+                );
+
+                return true;
+            }
+        };
+
+        if l.byte() == Some(b'\'') {
+            l.advance();
+        } else if let Some(end) = closing_quote(l.rest()) {
+            // Something like 'ab', skip to the quote that was meant to close it.
+            l.advance_with(end + 1);
+
+            let span = l.span_from(start);
+            l.error(
+                Diagnostic::error("character literal may only contain one character")
+                    .with_code(ErrorCode::UnterminatedChar)
+                    .with_label(span, "more than one character")
+                    .with_note("use \"\" for strings"),
+            );
         } else {
-            panic!("syntax error: missing quote");
+            let here = l.here();
+            l.error(
+                Diagnostic::error("missing quote at the end of a character literal")
+                    .with_code(ErrorCode::UnterminatedChar)
+                    .with_label(here, "expected `'`")
+                    .with_secondary(start, "character literal starts here")
+                    .with_note(
+                        "character literals can only hold one character, use \"\" for strings",
+                    ),
+            );
         }
+
+        l.add_token(Token {
+            kind: TokenKind::CharLiteral(res),
+            span: l.span_from(start),
+        });
+
+        return true;
     }
 
     false
 }
 
+/// The offset of the next `'` on the same line, skipping over escaped characters.
+fn closing_quote(rest: &str) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let mut i = 0;
+
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b'\'' => return Some(i),
+            b'\n' => return None,
+            b'\\' => i += 2,
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::{lex_char, lex_string};
+    use crate::diagnostics::Diagnostics;
     use crate::lexer::*;
+    use crate::span::{FileId, Span};
 
     #[test]
     fn test_lex_char() {
@@ -93,11 +384,11 @@ mod tests {
         let c3 = "'\''";
         let c4 = "'\\\\'";
 
-        let mut l0 = Lexer::new(c0);
-        let mut l1 = Lexer::new(c1);
-        let mut l2 = Lexer::new(c2);
-        let mut l3 = Lexer::new(c3);
-        let mut l4 = Lexer::new(c4);
+        let mut l0 = Lexer::new(c0, FileId(0));
+        let mut l1 = Lexer::new(c1, FileId(0));
+        let mut l2 = Lexer::new(c2, FileId(0));
+        let mut l3 = Lexer::new(c3, FileId(0));
+        let mut l4 = Lexer::new(c4, FileId(0));
 
         lex_char(&mut l0);
         lex_char(&mut l1);
@@ -105,45 +396,45 @@ mod tests {
         lex_char(&mut l3);
         lex_char(&mut l4);
 
-        let o0 = format!("{:?}", l0.tokens);
-        let o1 = format!("{:?}", l1.tokens);
-        let o2 = format!("{:?}", l2.tokens);
-        let o3 = format!("{:?}", l3.tokens);
-        let o4 = format!("{:?}", l4.tokens);
+        let o0 = format!("{:?}", vec![l0.next().unwrap().unwrap()]);
+        let o1 = format!("{:?}", vec![l1.next().unwrap().unwrap()]);
+        let o2 = format!("{:?}", vec![l2.next().unwrap().unwrap()]);
+        let o3 = format!("{:?}", vec![l3.next().unwrap().unwrap()]);
+        let o4 = format!("{:?}", vec![l4.next().unwrap().unwrap()]);
 
         let e0 = format!(
             "{:?}",
             vec![Token {
                 kind: TokenKind::CharLiteral('a'),
-                size: 3
+                span: Span::new(FileId(0), 0, 3, 1, 1)
             }]
         );
         let e1 = format!(
             "{:?}",
             vec![Token {
                 kind: TokenKind::CharLiteral('1'),
-                size: 3
+                span: Span::new(FileId(0), 0, 3, 1, 1)
             }]
         );
         let e2 = format!(
             "{:?}",
             vec![Token {
                 kind: TokenKind::CharLiteral(';'),
-                size: 3
+                span: Span::new(FileId(0), 0, 3, 1, 1)
             }]
         );
         let e3 = format!(
             "{:?}",
             vec![Token {
                 kind: TokenKind::CharLiteral('\''),
-                size: 3
+                span: Span::new(FileId(0), 0, 3, 1, 1)
             }]
         );
         let e4 = format!(
             "{:?}",
             vec![Token {
                 kind: TokenKind::CharLiteral('\\'),
-                size: 4
+                span: Span::new(FileId(0), 0, 4, 1, 1)
             }]
         );
 
@@ -159,30 +450,30 @@ mod tests {
         let str1 = "\"Hello, World!\";";
         let str2 = "\"Hello,\\\" World!\"";
 
-        let mut l0 = Lexer::new(str0);
-        let mut l1 = Lexer::new(str1);
-        let mut l2 = Lexer::new(str2);
+        let mut l0 = Lexer::new(str0, FileId(0));
+        let mut l1 = Lexer::new(str1, FileId(0));
+        let mut l2 = Lexer::new(str2, FileId(0));
 
         lex_string(&mut l0);
         lex_string(&mut l1);
         lex_string(&mut l2);
 
-        let o0 = format!("{:?}", l0.tokens);
-        let o1 = format!("{:?}", l1.tokens);
-        let o2 = format!("{:?}", l2.tokens);
+        let o0 = format!("{:?}", vec![l0.next().unwrap().unwrap()]);
+        let o1 = format!("{:?}", vec![l1.next().unwrap().unwrap()]);
+        let o2 = format!("{:?}", vec![l2.next().unwrap().unwrap()]);
 
         let expected = format!(
             "{:?}",
             vec![Token {
-                kind: TokenKind::StringLiteral("Hello, World!".to_string()),
-                size: 15
+                kind: TokenKind::StringLiteral("Hello, World!".into()),
+                span: Span::new(FileId(0), 0, 15, 1, 1)
             }]
         );
         let expected2 = format!(
             "{:?}",
             vec![Token {
-                kind: TokenKind::StringLiteral("Hello,\" World!".to_string()),
-                size: 17
+                kind: TokenKind::StringLiteral("Hello,\" World!".into()),
+                span: Span::new(FileId(0), 0, 17, 1, 1)
             }]
         );
 
@@ -197,10 +488,10 @@ mod tests {
         let str2 = ";\"Hello, World!\"";
         let str3 = "\"Hello,\\\" World!\"";
 
-        let t0 = lexer(str0);
-        let t1 = lexer(str1);
-        let t2 = lexer(str2);
-        let t3 = lexer(str3);
+        let t0 = lexer(str0, FileId(0), &mut Diagnostics::new());
+        let t1 = lexer(str1, FileId(0), &mut Diagnostics::new());
+        let t2 = lexer(str2, FileId(0), &mut Diagnostics::new());
+        let t3 = lexer(str3, FileId(0), &mut Diagnostics::new());
 
         let o0 = format!("{:?}", t0);
         let o1 = format!("{:?}", t1);
@@ -211,12 +502,12 @@ mod tests {
             "{:?}",
             vec![
                 Token {
-                    kind: TokenKind::StringLiteral("Hello, World!".to_string()),
-                    size: 15
+                    kind: TokenKind::StringLiteral("Hello, World!".into()),
+                    span: Span::new(FileId(0), 0, 15, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 15, 0, 1, 16)
                 }
             ]
         );
@@ -224,16 +515,16 @@ mod tests {
             "{:?}",
             vec![
                 Token {
-                    kind: TokenKind::StringLiteral("Hello, World!".to_string()),
-                    size: 15
+                    kind: TokenKind::StringLiteral("Hello, World!".into()),
+                    span: Span::new(FileId(0), 0, 15, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Semicolon,
-                    size: 1
+                    span: Span::new(FileId(0), 15, 1, 1, 16)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 16, 0, 1, 17)
                 }
             ]
         );
@@ -242,15 +533,15 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::Semicolon,
-                    size: 1
+                    span: Span::new(FileId(0), 0, 1, 1, 1)
                 },
                 Token {
-                    kind: TokenKind::StringLiteral("Hello, World!".to_string()),
-                    size: 15
+                    kind: TokenKind::StringLiteral("Hello, World!".into()),
+                    span: Span::new(FileId(0), 1, 15, 1, 2)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 16, 0, 1, 17)
                 }
             ]
         );
@@ -258,12 +549,12 @@ mod tests {
             "{:?}",
             vec![
                 Token {
-                    kind: TokenKind::StringLiteral("Hello,\" World!".to_string()),
-                    size: 17
+                    kind: TokenKind::StringLiteral("Hello,\" World!".into()),
+                    span: Span::new(FileId(0), 0, 17, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 17, 0, 1, 18)
                 }
             ]
         );
@@ -279,9 +570,9 @@ mod tests {
         let c1 = "'1'";
         let c2 = "';'";
 
-        let t0 = lexer(c0);
-        let t1 = lexer(c1);
-        let t2 = lexer(c2);
+        let t0 = lexer(c0, FileId(0), &mut Diagnostics::new());
+        let t1 = lexer(c1, FileId(0), &mut Diagnostics::new());
+        let t2 = lexer(c2, FileId(0), &mut Diagnostics::new());
 
         let o0 = format!("{:?}", t0);
         let o1 = format!("{:?}", t1);
@@ -292,11 +583,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::CharLiteral('a'),
-                    size: 3
+                    span: Span::new(FileId(0), 0, 3, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 3, 0, 1, 4)
                 }
             ]
         );
@@ -305,11 +596,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::CharLiteral('1'),
-                    size: 3
+                    span: Span::new(FileId(0), 0, 3, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 3, 0, 1, 4)
                 }
             ]
         );
@@ -318,11 +609,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::CharLiteral(';'),
-                    size: 3
+                    span: Span::new(FileId(0), 0, 3, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 3, 0, 1, 4)
                 }
             ]
         );
@@ -336,8 +627,8 @@ mod tests {
         let c0 = "'\\''";
         let c1 = "'\\\\'";
 
-        let t0 = lexer(c0);
-        let t1 = lexer(c1);
+        let t0 = lexer(c0, FileId(0), &mut Diagnostics::new());
+        let t1 = lexer(c1, FileId(0), &mut Diagnostics::new());
 
         let o0 = format!("{:?}", t0);
         let o1 = format!("{:?}", t1);
@@ -347,11 +638,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::CharLiteral('\''),
-                    size: 4
+                    span: Span::new(FileId(0), 0, 4, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 4, 0, 1, 5)
                 }
             ]
         );
@@ -360,11 +651,11 @@ mod tests {
             vec![
                 Token {
                     kind: TokenKind::CharLiteral('\\'),
-                    size: 4
+                    span: Span::new(FileId(0), 0, 4, 1, 1)
                 },
                 Token {
                     kind: TokenKind::Eof,
-                    size: 0
+                    span: Span::new(FileId(0), 4, 0, 1, 5)
                 }
             ]
         );
@@ -372,4 +663,265 @@ mod tests {
         assert_eq!(o0, e0, "Test case 1 failed");
         assert_eq!(o1, e1, "Test case 2 failed");
     }
+    #[test]
+    fn test_lexer_with_unterminated_characters() {
+        let mut d0 = Diagnostics::new();
+        let mut d1 = Diagnostics::new();
+
+        let t0 = lexer("'ab';", FileId(0), &mut d0);
+        let t1 = lexer("'", FileId(0), &mut d1);
+
+        assert_eq!(
+            d0.iter().map(|d| d.code).collect::<Vec<_>>(),
+            vec![Some(ErrorCode::UnterminatedChar)],
+            "Test case 1 failed"
+        );
+        assert_eq!(
+            d0.iter().next().unwrap().message,
+            "character literal may only contain one character",
+            "Test case 1 failed"
+        );
+        assert_eq!(
+            d0.iter().next().unwrap().primary_span(),
+            Some(Span::new(FileId(0), 0, 4, 1, 1)),
+            "Test case 1 failed"
+        );
+        assert_eq!(
+            format!("{:?}", t0.iter().map(|t| &t.kind).collect::<Vec<_>>()),
+            "[CharLiteral('a'), Semicolon, Eof]",
+            "Test case 1 failed"
+        );
+        assert_eq!(
+            d1.iter().map(|d| d.code).collect::<Vec<_>>(),
+            vec![Some(ErrorCode::UnterminatedChar)],
+            "Test case 2 failed"
+        );
+        assert_eq!(t1.len(), 1, "Test case 2 failed");
+    }
+    #[test]
+    fn test_lex_escapes() {
+        let cases = [
+            (r#""a\nb\tc\rd\0""#, "a\nb\tc\rd\0"),
+            (r#""\\ \' \"""#, "\\ ' \""),
+            (r#""\x41\x7f""#, "A\x7f"),
+            (r#""\u{48}\u{e9}\u{1F600}""#, "H\u{e9}\u{1F600}"),
+        ];
+
+        for (i, (code, expected)) in cases.iter().enumerate() {
+            let mut d = Diagnostics::new();
+            let tokens = lexer(code, FileId(0), &mut d);
+
+            assert!(d.is_empty(), "Test case {} failed", i + 1);
+            assert_eq!(
+                format!("{:?}", tokens[0].kind),
+                format!("{:?}", TokenKind::StringLiteral((*expected).into())),
+                "Test case {} failed",
+                i + 1
+            );
+        }
+
+        let chars = [(r"'\n'", '\n'), (r"'\x41'", 'A'), (r"'\u{e9}'", '\u{e9}')];
+
+        for (i, (code, expected)) in chars.iter().enumerate() {
+            let tokens = lexer(code, FileId(0), &mut Diagnostics::new());
+
+            assert_eq!(
+                format!("{:?}", tokens[0].kind),
+                format!("{:?}", TokenKind::CharLiteral(*expected)),
+                "Test case {} failed",
+                i + 5
+            );
+        }
+    }
+    #[test]
+    fn test_lexer_with_invalid_escapes() {
+        let cases = [
+            (r#""\q""#, "unknown character escape `\\q`", (1, 2)),
+            (r#""\x4""#, "numeric character escape is too short", (1, 3)),
+            (r#""\xFF""#, "out of range hex escape", (1, 4)),
+            (r#""\u41""#, "incorrect unicode escape sequence", (1, 2)),
+            (r#""\u{}""#, "incorrect unicode escape sequence", (1, 3)),
+            (r#""\u{D800}""#, "invalid unicode character escape", (1, 8)),
+            (r"'\q'", "unknown character escape `\\q`", (1, 2)),
+        ];
+
+        for (i, (code, message, (offset, len))) in cases.iter().enumerate() {
+            let mut d = Diagnostics::new();
+            let tokens = lexer(code, FileId(0), &mut d);
+            let errors = d.iter().collect::<Vec<_>>();
+
+            assert_eq!(errors.len(), 1, "Test case {} failed", i + 1);
+            assert_eq!(errors[0].code, Some(ErrorCode::InvalidEscape));
+            assert_eq!(errors[0].message, *message, "Test case {} failed", i + 1);
+            assert_eq!(
+                errors[0].primary_span().map(|s| (s.offset, s.len)),
+                Some((*offset, *len)),
+                "Test case {} failed",
+                i + 1
+            );
+            // The literal is still there, with the replacement character.
+            assert!(
+                format!("{:?}", tokens[0].kind).contains('\u{FFFD}'),
+                "Test case {} failed",
+                i + 1
+            );
+        }
+    }
+    #[test]
+    fn test_lexer_with_unterminated_strings() {
+        let mut d = Diagnostics::new();
+        let tokens = lexer("foo(\"abc", FileId(0), &mut d);
+
+        assert_eq!(
+            d.iter().map(|d| d.code).collect::<Vec<_>>(),
+            vec![Some(ErrorCode::UnterminatedString)]
+        );
+        assert_eq!(
+            d.iter().next().unwrap().primary_span(),
+            Some(Span::new(FileId(0), 4, 1, 1, 5))
+        );
+        // Nothing is dropped from the end.
+        assert_eq!(
+            format!("{:?}", tokens[2]),
+            format!(
+                "{:?}",
+                Token {
+                    kind: TokenKind::StringLiteral("abc".into()),
+                    span: Span::new(FileId(0), 4, 4, 1, 5)
+                }
+            )
+        );
+    }
+    #[test]
+    fn test_lex_raw_string() {
+        let cases = [
+            (r#"r"C:\path\n""#, r"C:\path\n", 12),
+            (r##"r#"say "hi""#"##, r#"say "hi""#, 13),
+            (r###"r##"a "# b"##;"###, r##"a "# b"##, 13),
+            ("r\"a\nb\"", "a\nb", 6),
+        ];
+
+        for (i, (code, expected, len)) in cases.iter().enumerate() {
+            let mut d = Diagnostics::new();
+            let tokens = lexer(code, FileId(0), &mut d);
+
+            assert!(d.is_empty(), "Test case {} failed", i + 1);
+            assert_eq!(
+                format!("{:?}", tokens[0]),
+                format!(
+                    "{:?}",
+                    Token {
+                        kind: TokenKind::StringLiteral((*expected).into()),
+                        span: Span::new(FileId(0), 0, *len, 1, 1)
+                    }
+                ),
+                "Test case {} failed",
+                i + 1
+            );
+        }
+    }
+    #[test]
+    fn test_lex_raw_string_is_not_an_identifier() {
+        // `#` isn't a valid character, it's reported and skipped.
+        let tokens = lexer("r r# r2\"a\"", FileId(0), &mut Diagnostics::new());
+
+        assert_eq!(
+            format!(
+                "{:?}",
+                tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+            ),
+            format!(
+                "{:?}",
+                vec![
+                    TokenKind::Unknown("r".into()),
+                    TokenKind::Unknown("r".into()),
+                    TokenKind::Unknown("r2".into()),
+                    TokenKind::StringLiteral("a".into()),
+                    TokenKind::Eof
+                ]
+            )
+        );
+    }
+    #[test]
+    fn test_lexer_with_unterminated_raw_strings() {
+        let mut d = Diagnostics::new();
+        let tokens = lexer("r#\"abc\"", FileId(0), &mut d);
+
+        assert_eq!(
+            d.iter().map(|d| d.code).collect::<Vec<_>>(),
+            vec![Some(ErrorCode::UnterminatedString)]
+        );
+        assert_eq!(
+            format!("{:?}", tokens[0].kind),
+            format!("{:?}", TokenKind::StringLiteral("abc\"".into()))
+        );
+    }
+    #[test]
+    fn test_lex_multi_line_strings() {
+        let code = "\"one\n two \\\n     three\\\r\n\tfour\"; a";
+        let tokens = lexer(code, FileId(0), &mut Diagnostics::new());
+
+        assert_eq!(
+            format!("{:?}", tokens[0].kind),
+            format!(
+                "{:?}",
+                TokenKind::StringLiteral("one\n two threefour".into())
+            )
+        );
+        // Lines are still counted.
+        assert_eq!(tokens[2].span.line, 4);
+        assert_eq!(tokens[2].span.col, 9);
+    }
+    #[test]
+    fn test_lex_string_borrows_from_the_source() {
+        let code = r#""plain" "esc\n" r"raw" "cont\
+            inued""#;
+        let tokens = lexer(code, FileId(0), &mut Diagnostics::new());
+
+        let borrowed = tokens
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::StringLiteral(s) => Some(matches!(s, Cow::Borrowed(_))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(borrowed, vec![true, false, true, false]);
+    }
+    #[test]
+    fn test_lex_non_ascii_strings_and_chars() {
+        let code = "\"日本語\" 'é' '🌍' \"a\\u{e9}ü\"";
+        let mut d = Diagnostics::new();
+        let tokens = lexer(code, FileId(0), &mut d);
+
+        assert!(d.is_empty());
+        assert_eq!(
+            format!("{:?}", tokens),
+            format!(
+                "{:?}",
+                vec![
+                    Token {
+                        kind: TokenKind::StringLiteral("日本語".into()),
+                        span: Span::new(FileId(0), 0, 11, 1, 1)
+                    },
+                    Token {
+                        kind: TokenKind::CharLiteral('é'),
+                        span: Span::new(FileId(0), 12, 4, 1, 7)
+                    },
+                    Token {
+                        kind: TokenKind::CharLiteral('🌍'),
+                        span: Span::new(FileId(0), 17, 6, 1, 11)
+                    },
+                    Token {
+                        kind: TokenKind::StringLiteral("aéü".into()),
+                        span: Span::new(FileId(0), 24, 11, 1, 15)
+                    },
+                    Token {
+                        kind: TokenKind::Eof,
+                        span: Span::new(FileId(0), 35, 0, 1, 25)
+                    },
+                ]
+            )
+        );
+    }
 }
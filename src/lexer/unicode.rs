@@ -0,0 +1,294 @@
+//! Identifiers that aren't ASCII, following UAX #31 (what can be in an identifier) and
+//! UTS #39 (which identifiers can be mistaken for each other).
+//!
+//! ASCII identifiers never need any of this, so the lexer only comes here for the rest.
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use unicode_security::mixed_script::AugmentedScriptSet;
+use unicode_security::skeleton;
+
+use super::Lexer;
+use crate::diagnostics::{Diagnostic, ErrorCode};
+use crate::span::Span;
+
+/// Can `c` start an identifier? `_` can too, but that's ASCII.
+#[inline]
+pub fn is_ident_start(c: char) -> bool {
+    unicode_ident::is_xid_start(c)
+}
+
+/// Can `c` be in an identifier after the first character?
+#[inline]
+pub fn is_ident_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// Identifiers are compared after NFC normalization, so `é` written as one character and as
+/// `e` with a combining accent are the same identifier. Only allocates if something changed.
+pub fn normalize(ident: &str) -> Cow<'_, str> {
+    if ident.is_ascii() || is_nfc_quick(ident.chars()) == IsNormalized::Yes {
+        return Cow::Borrowed(ident);
+    }
+
+    let normalized = ident.nfc().collect::<String>();
+
+    if normalized == ident {
+        Cow::Borrowed(ident)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// Identifiers the confusable lint has seen.
+#[derive(Debug, Default)]
+pub struct Identifiers<'src> {
+    /// Every different identifier and where it was first used.
+    seen: HashMap<Cow<'src, str>, Span>,
+    /// What the identifiers look like (their skeleton) and the first identifier that looked
+    /// like it. Only two identifiers that aren't both ASCII are reported, so this isn't built
+    /// until an identifier that isn't ASCII shows up.
+    skeletons: Option<HashMap<String, Cow<'src, str>>>,
+}
+
+/// Warns about identifiers that are easy to mistake for something else: ones that mix
+/// scripts, like `pаypal` with a Cyrillic `а`, and ones that look the same as another
+/// identifier in the file but aren't.
+pub fn lint_identifier<'src>(l: &mut Lexer<'src>, ident: Cow<'src, str>, span: Span) {
+    if l.identifiers.seen.contains_key(&ident) {
+        return;
+    }
+
+    l.identifiers.seen.insert(ident.clone(), span);
+
+    if !ident.is_ascii() {
+        lint_mixed_script(l, &ident, span);
+    }
+
+    let identifiers = &mut l.identifiers;
+
+    if identifiers.skeletons.is_none() {
+        if ident.is_ascii() {
+            return;
+        }
+
+        // Sorted so the first one is always the one that was used first.
+        let mut seen = identifiers.seen.iter().collect::<Vec<_>>();
+        seen.sort_by_key(|(_, span)| span.offset);
+
+        let mut skeletons = HashMap::new();
+
+        for (other, _) in seen.into_iter().filter(|(other, _)| **other != ident) {
+            skeletons
+                .entry(skeleton(other).collect())
+                .or_insert_with(|| other.clone());
+        }
+
+        identifiers.skeletons = Some(skeletons);
+    }
+
+    let skeletons = identifiers.skeletons.as_mut().unwrap();
+    let other = match skeletons.get(&skeleton(&ident).collect::<String>()) {
+        Some(other) if !(other.is_ascii() && ident.is_ascii()) => other.clone(),
+        Some(_) => return,
+        None => {
+            skeletons.insert(skeleton(&ident).collect(), ident);
+            return;
+        }
+    };
+
+    let other_span = identifiers.seen[&other];
+
+    l.error(
+        Diagnostic::warning(format!(
+            "identifier `{}` can be confused with `{}`",
+            ident, other
+        ))
+        .with_code(ErrorCode::ConfusableIdentifier)
+        .with_label(span, "this identifier")
+        .with_secondary(other_span, "looks like this one")
+        .with_note("they look the same, but are made of different characters"),
+    );
+}
+
+/// Warns if `ident` has characters of more than one script in it. Common characters like
+/// digits and `_` go with any script, and so do the scripts that are written together, like
+/// Han and Hiragana in Japanese.
+fn lint_mixed_script(l: &mut Lexer, ident: &str, span: Span) {
+    let mut scripts = AugmentedScriptSet::default();
+
+    for c in ident.chars() {
+        let before = scripts;
+
+        scripts.intersect_with(AugmentedScriptSet::for_char(c));
+
+        if scripts.is_empty() {
+            l.error(
+                Diagnostic::warning(format!("identifier `{}` mixes scripts", ident))
+                    .with_code(ErrorCode::MixedScriptIdentifier)
+                    .with_label(
+                        span,
+                        format!(
+                            "`{}` is {}, but the characters before it are {}",
+                            c,
+                            AugmentedScriptSet::for_char(c),
+                            before
+                        ),
+                    )
+                    .with_note("letters of different scripts can look the same, like Latin `a` and Cyrillic `а`"),
+            );
+
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{Diagnostics, Severity};
+    use crate::lexer::{lexer, Lexer, TokenKind};
+    use crate::span::FileId;
+
+    #[test]
+    fn test_normalize() {
+        let cases = [
+            ("abc", "abc", true),
+            ("caf\u{e9}", "caf\u{e9}", true),
+            ("cafe\u{301}", "caf\u{e9}", false),
+            ("\u{212b}", "\u{c5}", false),
+        ];
+
+        for (i, (ident, expected, borrowed)) in cases.iter().enumerate() {
+            let normalized = normalize(ident);
+
+            assert_eq!(normalized, *expected, "Test case {} failed", i + 1);
+            assert_eq!(
+                matches!(normalized, Cow::Borrowed(_)),
+                *borrowed,
+                "Test case {} failed",
+                i + 1
+            );
+        }
+    }
+    #[test]
+    fn test_lex_unicode_identifiers() {
+        let mut d = Diagnostics::new();
+        let tokens = lexer(
+            "größe _名前 счётчик2 x\u{301} cafe\u{301} 1ö",
+            FileId(0),
+            &mut d,
+        );
+
+        assert!(d.is_empty());
+        assert_eq!(
+            format!(
+                "{:?}",
+                tokens.into_iter().map(|t| t.kind).collect::<Vec<_>>()
+            ),
+            format!(
+                "{:?}",
+                vec![
+                    TokenKind::Unknown("größe".into()),
+                    TokenKind::Unknown("_名前".into()),
+                    TokenKind::Unknown("счётчик2".into()),
+                    TokenKind::Unknown("x\u{301}".into()),
+                    TokenKind::Unknown("caf\u{e9}".into()),
+                    TokenKind::IntegerLiteral(1, crate::parser::Type::None),
+                    TokenKind::Unknown("ö".into()),
+                    TokenKind::Eof,
+                ]
+            )
+        );
+    }
+    #[test]
+    fn test_lex_identifier_can_not_start_with_a_mark() {
+        let mut d = Diagnostics::new();
+        let tokens = lexer("\u{301}a 🦀", FileId(0), &mut d);
+
+        let codes = d.iter().map(|d| d.code).collect::<Vec<_>>();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            codes,
+            vec![
+                Some(ErrorCode::UnknownCharacter),
+                Some(ErrorCode::UnknownCharacter)
+            ]
+        );
+    }
+    #[test]
+    fn test_lint_mixed_script() {
+        let mut d = Diagnostics::new();
+
+        // Cyrillic `а` in the middle of Latin, and Japanese which is fine.
+        lexer("pаypal ひらがな漢字カタカナ", FileId(0), &mut d);
+
+        let warnings = d.iter().collect::<Vec<_>>();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+        assert_eq!(warnings[0].message, "identifier `pаypal` mixes scripts");
+        assert_eq!(
+            warnings[0].labels[0].message,
+            "`а` is Cyrillic, but the characters before it are Latin"
+        );
+    }
+    #[test]
+    fn test_lint_warnings_do_not_hide_tokens() {
+        let results = Lexer::new("pаypal = 1;", FileId(0))
+            .map(|r| match r {
+                Ok(token) => Ok(token.kind.to_string()),
+                Err(e) => Err(e.severity),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            results,
+            vec![
+                Err(Severity::Warning),
+                Ok("identifier `pаypal`".to_string()),
+                Ok("`=`".to_string()),
+                Ok("integer `1`".to_string()),
+                Ok("`;`".to_string()),
+                Ok("end of file".to_string()),
+            ]
+        );
+    }
+    #[test]
+    fn test_lint_confusable_identifiers() {
+        let cases = [
+            // Cyrillic `р`, `а` and Latin `r`
+            ("par раr", Some(("раr", "par"))),
+            // The other way around.
+            ("раr par", Some(("par", "раr"))),
+            // Both ASCII, `rn` looks like `m` but that's left alone.
+            ("modern modem", None),
+            ("ö ö o", None),
+        ];
+
+        for (i, (code, expected)) in cases.iter().enumerate() {
+            let mut d = Diagnostics::new();
+
+            lexer(code, FileId(0), &mut d);
+
+            let found = d
+                .iter()
+                .filter(|d| d.code == Some(ErrorCode::ConfusableIdentifier))
+                .map(|d| (d.message.clone(), d.labels[1].span.offset))
+                .collect::<Vec<_>>();
+            let expected = expected
+                .iter()
+                .map(|(a, b)| {
+                    (
+                        format!("identifier `{}` can be confused with `{}`", a, b),
+                        code.find(b).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(found, expected, "Test case {} failed", i + 1);
+        }
+    }
+}
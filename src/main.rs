@@ -1,26 +1,73 @@
-#![feature(box_syntax)]
+use niff::diagnostics::render::{ColorChoice, Renderer};
+use niff::diagnostics::Diagnostics;
+use niff::lexer::Lexer;
+use niff::parser;
+use niff::span::{FileId, SourceMap};
 
-mod lexer;
-mod parser;
+const USAGE: &str = "usage: niff [--color=auto|always|never] <file>";
 
-fn build(src: &str) -> Result<(), String> {
-    let tokens = lexer::lexer(src);
-    let _ir = parser::parser(tokens);
+fn build(sources: &SourceMap, file: FileId, diagnostics: &mut Diagnostics) {
+    let lexer = Lexer::new(&sources.get(file).src, file);
+    let _ir = parser::parser(lexer, diagnostics);
     // let assembly = compiler();
     // let binary = linker();
-
-    Ok(())
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
+    let mut color = ColorChoice::Auto;
+    let mut path = None;
 
-    if args.len() < 2 {
-        println!("not enough arguments");
-        std::process::exit(1)
+    for arg in std::env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--color=") {
+            color = match ColorChoice::from_arg(value) {
+                Some(color) => color,
+                None => {
+                    eprintln!("error: invalid value for --color: {}\n{}", value, USAGE);
+                    std::process::exit(1)
+                }
+            };
+        } else if arg.starts_with("--") || path.is_some() {
+            eprintln!("error: unexpected argument: {}\n{}", arg, USAGE);
+            std::process::exit(1)
+        } else {
+            path = Some(arg);
+        }
     }
 
-    let src = std::fs::read_to_string(args[1].clone()).unwrap();
+    let path = match path {
+        Some(path) => path,
+        None => {
+            eprintln!("not enough arguments\n{}", USAGE);
+            std::process::exit(1)
+        }
+    };
+
+    let src = match std::fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("error: couldn't read {}: {}", path, e);
+            std::process::exit(1)
+        }
+    };
+
+    let mut sources = SourceMap::new();
+    let file = sources.add(path, src);
+
+    let mut diagnostics = Diagnostics::new();
+
+    build(&sources, file, &mut diagnostics);
 
-    let _result = build(&src);
+    let renderer = Renderer::new(&sources, color.use_color());
+
+    for diagnostic in diagnostics.iter() {
+        eprintln!("{}", renderer.render(diagnostic));
+    }
+
+    if diagnostics.has_errors() {
+        eprint!(
+            "{}",
+            renderer.render_summary(&sources.get(file).name, diagnostics.error_count())
+        );
+        std::process::exit(1)
+    }
 }
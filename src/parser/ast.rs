@@ -0,0 +1,163 @@
+//! The tree the parser produces.
+//!
+//! Every node has a span, so later stages can point at the source when they find an error.
+//! Nodes that couldn't be parsed are kept as `Error` nodes, the error has already been
+//! reported when they are created.
+
+use super::expressions::{OpKind, UnaryOp};
+use super::{Modifiers, Type};
+use crate::span::Span;
+
+/// A whole source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    /// From the `//!` comments at the start of the file.
+    pub doc: Option<String>,
+    pub items: Vec<Item>,
+}
+
+/// Things that can be at the top level of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Fn(FnDecl),
+    /// A global variable.
+    Global(Let),
+    Error(Span),
+}
+
+/// A function definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnDecl {
+    /// From the `///` comments before it, one line per comment.
+    pub doc: Option<String>,
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_type: Type,
+    /// `None` for declarations without a body, `fn foo() i32;`.
+    pub body: Option<Block>,
+    pub span: Span,
+}
+
+/// A function parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub modifiers: Vec<Modifiers>,
+    /// `Type::None` if it wasn't specified.
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// `{ ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Let(Let),
+    /// An expression followed by a `;`.
+    Expr(Expr),
+    /// `return` with an optional value. (value, span)
+    Return(Option<Expr>, Span),
+    If(If),
+    While(While),
+    For(For),
+    Break(Span),
+    Continue(Span),
+    /// A nested block.
+    Block(Block),
+    Error(Span),
+}
+
+/// A variable declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Let {
+    /// From the `///` comments before it, one line per comment.
+    pub doc: Option<String>,
+    pub name: String,
+    pub modifiers: Vec<Modifiers>,
+    /// `Type::None` if it wasn't specified.
+    pub ty: Type,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// `if (cond) { } elif (cond) { } else { }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    pub cond: Expr,
+    pub then: Block,
+    pub elifs: Vec<Elif>,
+    pub else_: Option<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Elif {
+    pub cond: Expr,
+    pub body: Block,
+    pub span: Span,
+}
+
+/// `while (cond) { }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct While {
+    pub cond: Expr,
+    pub body: Block,
+    pub span: Span,
+}
+
+/// `for var in start..end { }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct For {
+    pub var: String,
+    pub start: Expr,
+    pub end: Expr,
+    /// Is it `..=` instead of `..`?
+    pub inclusive: bool,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// (value, type suffix or `Type::None`)
+    Int(i64, Type),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Char(char),
+    Ident(String),
+    /// (operator, left, right)
+    Binary(OpKind, Box<Expr>, Box<Expr>),
+    /// (operator, operand)
+    Unary(UnaryOp, Box<Expr>),
+    /// `a = b`, or `a += b` and the other compound assignments with their operator.
+    /// (operator, target, value)
+    Assign(Option<OpKind>, Box<Expr>, Box<Expr>),
+    Call(Call),
+}
+
+/// A function call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// The function to be called.
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+/// An argument passed to a function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub modifiers: Vec<Modifiers>,
+    pub value: Expr,
+}
@@ -1,97 +1,196 @@
+use super::ast::{Elif, Expr, If};
 use super::expressions::*;
+use super::statements::parse_block;
 use super::*;
 
-/// Parses an if statement.
-pub fn parse_if_statement(p: &mut Parser) {
-    // if (expr) {}
-    if p.tokens[p.i].kind == TokenKind::If && p.tokens[p.i + 1].kind == TokenKind::LeftParen {
-        p.advance_with(2);
+/// Parses a condition in parentheses, `(expr)`.
+pub fn parse_condition(p: &mut Parser) -> PResult<Expr> {
+    if p.token.kind != TokenKind::LeftParen {
+        return Err(p.unexpected("`(`"));
+    }
+
+    p.advance();
+
+    let cond = parse_expression(p)?;
+
+    if p.token.kind != TokenKind::RightParen {
+        return Err(p.unexpected("`)`"));
+    }
+
+    p.advance();
+
+    Ok(cond)
+}
 
-        let mut expr = Vec::new();
+/// Parses an if statement with all of its `elif` and `else` branches.
+pub fn parse_if_statement(p: &mut Parser) -> PResult<If> {
+    // if (expr) {} elif (expr) {} else {}
+    let start = p.token.span;
 
-        // while p.tokens[p.i].kind != TokenKind::RightParen {
-        if p.len < p.i {
-            panic!("syntax error: missing ')'");
-        }
+    if p.token.kind != TokenKind::If {
+        return Err(p.unexpected("`if`"));
+    }
+
+    p.advance();
+
+    let cond = parse_condition(p)?;
+    let then = parse_block(p)?;
+
+    let mut elifs = Vec::new();
 
-        // expr.push(p.tokens[p.i]);
-        // }
+    while p.token.kind == TokenKind::Elif {
+        let elif_start = p.token.span;
 
-        let condition = parse_expression(expr);
+        p.advance();
 
-        if p.tokens[p.i].kind != TokenKind::RightParen {
-            panic!("syntax error")
-        }
+        let cond = parse_condition(p)?;
+        let body = parse_block(p)?;
 
-        p.add_token(ParsedToken::If(condition));
+        elifs.push(Elif {
+            cond,
+            span: elif_start.to(body.span),
+            body,
+        });
     }
+
+    let else_ = if p.token.kind == TokenKind::Else {
+        p.advance();
+
+        Some(parse_block(p)?)
+    } else {
+        None
+    };
+
+    Ok(If {
+        cond,
+        then,
+        elifs,
+        else_,
+        span: start.to(p.prev_span()),
+    })
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use crate::{
-//         lexer::*,
-//         parser::{conditionals::parse_if_statement, Parser},
-//     };
-//
-//     #[test]
-//     fn test_parse_if_statement() {
-//         let code = r#"if (a > b) {}"#;
-//
-//         let tokens = lexer(code);
-//
-//         let a = format!("{:?}", tokens);
-//         let b = format!(
-//             "{:?}",
-//             vec![
-//                 Token {
-//                     kind: TokenKind::If,
-//                     size: 2
-//                 },
-//                 Token {
-//                     kind: TokenKind::LeftParen,
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::Unknown("a".to_string()),
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::GreaterThan,
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::Unknown("b".to_string()),
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::RightParen,
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::LeftBracket,
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::RightBracket,
-//                     size: 1
-//                 },
-//                 Token {
-//                     kind: TokenKind::Eof,
-//                     size: 0
-//                 }
-//             ]
-//         );
-//
-//         assert_eq!(a, b);
-//
-//         let mut parser = Parser::new(tokens);
-//
-//         parse_if_statement(&mut parser);
-//
-//         let c = format!("{:?}", parser.output);
-//         let d = "[If()]".to_string();
-//
-//         assert_eq!(c, d);
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostics::Diagnostics,
+        lexer::*,
+        parser::{ast::ExprKind, conditionals::parse_if_statement, Parser},
+        span::{FileId, Span},
+    };
+
+    #[test]
+    fn test_parse_if_statement() {
+        let code = r#"if (a > b) {}"#;
+
+        let tokens = lexer(code, FileId(0), &mut Diagnostics::new());
+
+        let a = format!("{:?}", tokens);
+        let b = format!(
+            "{:?}",
+            vec![
+                Token {
+                    kind: TokenKind::If,
+                    span: Span::new(FileId(0), 0, 2, 1, 1)
+                },
+                Token {
+                    kind: TokenKind::LeftParen,
+                    span: Span::new(FileId(0), 3, 1, 1, 4)
+                },
+                Token {
+                    kind: TokenKind::Unknown("a".into()),
+                    span: Span::new(FileId(0), 4, 1, 1, 5)
+                },
+                Token {
+                    kind: TokenKind::GreaterThan,
+                    span: Span::new(FileId(0), 6, 1, 1, 7)
+                },
+                Token {
+                    kind: TokenKind::Unknown("b".into()),
+                    span: Span::new(FileId(0), 8, 1, 1, 9)
+                },
+                Token {
+                    kind: TokenKind::RightParen,
+                    span: Span::new(FileId(0), 9, 1, 1, 10)
+                },
+                Token {
+                    kind: TokenKind::LeftBracket,
+                    span: Span::new(FileId(0), 11, 1, 1, 12)
+                },
+                Token {
+                    kind: TokenKind::RightBracket,
+                    span: Span::new(FileId(0), 12, 1, 1, 13)
+                },
+                Token {
+                    kind: TokenKind::Eof,
+                    span: Span::new(FileId(0), 13, 0, 1, 14)
+                }
+            ]
+        );
+
+        assert_eq!(a, b);
+
+        let mut parser = Parser::new(Lexer::new(code, FileId(0)));
+
+        let c = parse_if_statement(&mut parser).unwrap();
+
+        assert!(matches!(c.cond.kind, ExprKind::Binary(..)));
+        assert!(c.then.stmts.is_empty());
+        assert!(c.elifs.is_empty());
+        assert_eq!(c.else_, None);
+        assert_eq!(c.span, Span::new(FileId(0), 0, 13, 1, 1));
+    }
+    #[test]
+    fn test_parse_if_elif_else() {
+        let code = "if (a) { b; } elif (c) { d; } elif (e) {} else { f; } g;";
+
+        let mut parser = Parser::new(Lexer::new(code, FileId(0)));
+
+        let c = parse_if_statement(&mut parser).unwrap();
+
+        let conds = c
+            .elifs
+            .iter()
+            .map(|e| e.cond.kind.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(c.cond.kind, ExprKind::Ident("a".to_string()));
+        assert_eq!(c.then.stmts.len(), 1);
+        assert_eq!(
+            conds,
+            vec![
+                ExprKind::Ident("c".to_string()),
+                ExprKind::Ident("e".to_string())
+            ]
+        );
+        assert_eq!(c.else_.map(|b| b.stmts.len()), Some(1));
+        assert_eq!(c.span, Span::new(FileId(0), 0, 53, 1, 1));
+        assert_eq!(parser.token.kind, TokenKind::Unknown("".into()));
+    }
+    #[test]
+    fn test_parse_if_errors() {
+        let messages = [
+            "if a {}",
+            "if (a) b;",
+            "if (a) {} elif {}",
+            "if (a) {} else (b) {}",
+        ]
+        .iter()
+        .map(|code| {
+            let mut parser = Parser::new(Lexer::new(code, FileId(0)));
+
+            parse_if_statement(&mut parser).unwrap_err().message
+        })
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "expected `(`, found identifier `a`",
+                "expected `{`, found identifier `b`",
+                "expected `(`, found `{`",
+                "expected `{`, found `(`",
+            ]
+        );
+    }
+}
@@ -1,87 +1,21 @@
-use crate::lexer::{self, TokenKind};
+//! Expressions are parsed with precedence climbing (a Pratt parser): an operand is parsed,
+//! then operators are taken for as long as they bind tighter than the operator the operand
+//! belongs to. The precedence levels are the ones in `grammar-spec.md`.
+//!
+//! Operations on constants are folded while parsing, so `2 * 3` becomes `6`. `&&` and `||`
+//! short-circuit when they are folded too: `false && f()` is `false`, `f()` is never called.
 
-/// List of tokens used internally.
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Token {
-    Num(i64),
-    Boolean(bool),
-    Ident(String),
-    Operator(OpKind),
-    LeftParen,
-    RightParen,
-    Other(OpKind, Box<Token>, Box<Token>),
-}
-
-impl Token {
-    fn is_number(&self) -> bool {
-        matches!(self, Token::Num(_))
-    }
-    fn is_boolean(&self) -> bool {
-        matches!(self, Token::Boolean(_))
-    }
-    fn is_identifier(&self) -> bool {
-        matches!(self, Token::Ident(_))
-    }
-    fn is_operator(&self) -> bool {
-        matches!(self, Token::Operator(_))
-    }
-    fn precedence(&self) -> u8 {
-        // Note to self: Unary operators should have the highest priority
-        match self {
-            Self::Operator(OpKind::BitOr) => 3,
-            Self::Operator(OpKind::BitXor) => 4,
-            Self::Operator(OpKind::BitAnd) => 5,
-            Self::Operator(OpKind::Equals) | Self::Operator(OpKind::NotEquals) => 6,
-            Self::Operator(OpKind::Gt)
-            | Self::Operator(OpKind::Lt)
-            | Self::Operator(OpKind::GtEq)
-            | Self::Operator(OpKind::LtEq) => 7,
-            Self::Operator(OpKind::Sub) | Self::Operator(OpKind::Add) => 8,
-            Self::Operator(OpKind::Div)
-            | Self::Operator(OpKind::Mul)
-            | Self::Operator(OpKind::Mod) => 10,
-            Self::LeftParen | Self::RightParen => 13,
-            _ => unimplemented!(),
-        }
-    }
-    fn is_unary(&self) -> bool {
-        if !self.is_operator() {
-            return false;
-        }
-
-        matches!(
-            self,
-            Self::Operator(OpKind::Add)
-                | Self::Operator(OpKind::Sub)
-                | Self::Operator(OpKind::Mul)
-                | Self::Operator(OpKind::Div)
-                | Self::Operator(OpKind::Mod)
-                | Self::Operator(OpKind::BitXor)
-                | Self::Operator(OpKind::BitAnd)
-                | Self::Operator(OpKind::BitOr)
-                | Self::Operator(OpKind::Equals)
-                | Self::Operator(OpKind::NotEquals)
-                | Self::Operator(OpKind::GtEq)
-                | Self::Operator(OpKind::LtEq)
-                | Self::Operator(OpKind::Gt)
-                | Self::Operator(OpKind::Lt)
-        )
-    }
-    fn is_binary(&self) -> bool {
-        if !self.is_operator() {
-            return false;
-        }
+use std::fmt;
 
-        matches!(
-            self,
-            Self::Operator(OpKind::Inc) | Self::Operator(OpKind::Dec)
-        )
-    }
-}
+use super::ast::{Expr, ExprKind};
+use super::{functions, PResult, Parser, Type};
+use crate::diagnostics::{Diagnostic, ErrorCode};
+use crate::lexer::TokenKind;
+use crate::span::Span;
 
-/// List of operator kinds used internally.
+/// Operator kinds.
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum OpKind {
+pub enum OpKind {
     Add,
     Sub,
     Mul,
@@ -96,381 +30,1086 @@ enum OpKind {
     LtEq,
     Gt,
     Lt,
-    Inc,
-    Dec,
+    /// `<<`
+    Shl,
+    /// `>>`, keeps the sign.
+    Shr,
+    /// `**`
+    Pow,
+    /// `&&`, the right side is only evaluated if the left side is true.
+    And,
+    /// `||`, the right side is only evaluated if the left side is false.
+    Or,
 }
 
-/// Is this a number, an identifier or another expression?
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ExprKind {
-    Num(i64),
-    Boolean(bool),
-    Ident(String),
-    Expr(Box<ExprToken>),
+impl fmt::Display for OpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            OpKind::Add => "+",
+            OpKind::Sub => "-",
+            OpKind::Mul => "*",
+            OpKind::Div => "/",
+            OpKind::Mod => "%",
+            OpKind::BitAnd => "&",
+            OpKind::BitXor => "^",
+            OpKind::BitOr => "|",
+            OpKind::Equals => "==",
+            OpKind::NotEquals => "!=",
+            OpKind::GtEq => ">=",
+            OpKind::LtEq => "<=",
+            OpKind::Gt => ">",
+            OpKind::Lt => "<",
+            OpKind::Shl => "<<",
+            OpKind::Shr => ">>",
+            OpKind::Pow => "**",
+            OpKind::And => "&&",
+            OpKind::Or => "||",
+        };
+
+        write!(f, "{}", op)
+    }
 }
 
+/// Operators with one operand.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ExprToken {
-    Add(ExprKind, ExprKind),
-    Sub(ExprKind, ExprKind),
-    Mul(ExprKind, ExprKind),
-    Div(ExprKind, ExprKind),
-    Mod(ExprKind, ExprKind),
-    BitXor(ExprKind, ExprKind),
-    BitOr(ExprKind, ExprKind),
-    BitAnd(ExprKind, ExprKind),
-    Equals(ExprKind, ExprKind),
-    NotEquals(ExprKind, ExprKind),
-    GtEq(ExprKind, ExprKind),
-    LtEq(ExprKind, ExprKind),
-    Gt(ExprKind, ExprKind),
-    Lt(ExprKind, ExprKind),
-    Inc(ExprKind),
-    Dec(ExprKind),
-    LeftParen,
-    RightParen,
-    Other(ExprKind),
-    None,
+pub enum UnaryOp {
+    /// `-a`
+    Neg,
+    /// `!a`
+    Not,
+    /// `~a`
+    BitNot,
+    /// `++a`
+    PreInc,
+    /// `--a`
+    PreDec,
+    /// `a++`
+    PostInc,
+    /// `a--`
+    PostDec,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Expression {
-    expr: ExprToken,
-}
-
-fn tokenize(input: Vec<lexer::Token>) -> Vec<Token> {
-    let mut res = Vec::new();
-    let mut i = 0;
-
-    while i < input.len() {
-        match &input[i].kind {
-            lexer::TokenKind::Plus => res.push(Token::Operator(OpKind::Add)),
-            lexer::TokenKind::Minus => res.push(Token::Operator(OpKind::Sub)),
-            lexer::TokenKind::Slash => res.push(Token::Operator(OpKind::Div)),
-            lexer::TokenKind::Star => res.push(Token::Operator(OpKind::Mul)),
-            lexer::TokenKind::Percent => res.push(Token::Operator(OpKind::Mod)),
-            lexer::TokenKind::Caret => res.push(Token::Operator(OpKind::BitXor)),
-            lexer::TokenKind::And => res.push(Token::Operator(OpKind::BitAnd)),
-            lexer::TokenKind::Pipe => res.push(Token::Operator(OpKind::BitOr)),
-            lexer::TokenKind::DoubleEquals => res.push(Token::Operator(OpKind::Equals)),
-            lexer::TokenKind::BangEquals => res.push(Token::Operator(OpKind::NotEquals)),
-            lexer::TokenKind::GtEq => res.push(Token::Operator(OpKind::GtEq)),
-            lexer::TokenKind::LtEq => res.push(Token::Operator(OpKind::LtEq)),
-            lexer::TokenKind::GreaterThan => res.push(Token::Operator(OpKind::Gt)),
-            lexer::TokenKind::LessThan => res.push(Token::Operator(OpKind::Lt)),
-            lexer::TokenKind::LeftParen => res.push(Token::LeftParen),
-            lexer::TokenKind::RightParen => res.push(Token::RightParen),
-            token if *token == TokenKind::IntegerLiteral(0) => match token {
-                TokenKind::IntegerLiteral(a) => res.push(Token::Num(*a)),
-                _ => unreachable!(),
-            },
-            token if *token == TokenKind::True => res.push(Token::Boolean(true)),
-            token if *token == TokenKind::False => res.push(Token::Boolean(false)),
-            token if *token == TokenKind::Unknown("".to_string()) => match token {
-                TokenKind::Unknown(a) => res.push(Token::Ident(a.to_string())),
-                _ => unreachable!(),
-            },
-            token if *token == TokenKind::Eof => break,
-            _token => panic!("invalid token: {:?}", _token),
+impl UnaryOp {
+    /// Does it change the variable it's used on?
+    fn is_increment(&self) -> bool {
+        matches!(
+            self,
+            UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec
+        )
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+            UnaryOp::BitNot => write!(f, "~"),
+            UnaryOp::PreInc | UnaryOp::PostInc => write!(f, "++"),
+            UnaryOp::PreDec | UnaryOp::PostDec => write!(f, "--"),
         }
+    }
+}
+
+/// How strongly prefix operators bind to their operand. They are at level 11, so `-a * b` is
+/// `(-a) * b`, but `**` at level 12 binds more strongly.
+const PREFIX_BP: u8 = 11 * 2;
 
-        i += 1;
+/// The prefix operator `kind` is.
+fn prefix_op(kind: &TokenKind) -> Option<UnaryOp> {
+    match kind {
+        TokenKind::Minus => Some(UnaryOp::Neg),
+        TokenKind::Bang => Some(UnaryOp::Not),
+        TokenKind::Tilde => Some(UnaryOp::BitNot),
+        TokenKind::DoublePlus => Some(UnaryOp::PreInc),
+        TokenKind::DoubleMinus => Some(UnaryOp::PreDec),
+        _ => None,
     }
+}
 
-    res
+/// The postfix operator `kind` is. They bind more strongly than anything else, `-a++` is
+/// `-(a++)`.
+fn postfix_op(kind: &TokenKind) -> Option<UnaryOp> {
+    match kind {
+        TokenKind::DoublePlus => Some(UnaryOp::PostInc),
+        TokenKind::DoubleMinus => Some(UnaryOp::PostDec),
+        _ => None,
+    }
 }
 
-/// Turn infix syntax into postfix syntax using shunting yard algorithm.
-fn convert(input: Vec<Token>) -> Vec<Token> {
-    let mut operator_stack: Vec<Token> = Vec::new();
-    let mut output_queue = Vec::new();
+/// The binary operator `kind` is, with how strongly it binds to the operands on its (left,
+/// right). Binding more strongly on the right makes it left associative, `a - b - c` is
+/// `(a - b) - c`, and the other way around makes it right associative.
+fn binary_op(kind: &TokenKind) -> Option<(OpKind, u8, u8)> {
+    // The precedence from grammar-spec.md, left associative.
+    let (op, precedence) = match kind {
+        TokenKind::DoublePipe => (OpKind::Or, 1),
+        TokenKind::DoubleAnd => (OpKind::And, 2),
+        TokenKind::Pipe => (OpKind::BitOr, 3),
+        TokenKind::Caret => (OpKind::BitXor, 4),
+        TokenKind::And => (OpKind::BitAnd, 5),
+        TokenKind::DoubleEquals => (OpKind::Equals, 6),
+        TokenKind::BangEquals => (OpKind::NotEquals, 6),
+        TokenKind::GreaterThan => (OpKind::Gt, 7),
+        TokenKind::GtEq => (OpKind::GtEq, 7),
+        TokenKind::LessThan => (OpKind::Lt, 7),
+        TokenKind::LtEq => (OpKind::LtEq, 7),
+        TokenKind::Plus => (OpKind::Add, 8),
+        TokenKind::Minus => (OpKind::Sub, 8),
+        TokenKind::ShiftLeft => (OpKind::Shl, 9),
+        TokenKind::ShiftRight => (OpKind::Shr, 9),
+        TokenKind::Star => (OpKind::Mul, 10),
+        TokenKind::Slash => (OpKind::Div, 10),
+        TokenKind::Percent => (OpKind::Mod, 10),
+        // `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+        TokenKind::DoubleStar => return Some((OpKind::Pow, 12 * 2 + 1, 12 * 2)),
+        _ => return None,
+    };
 
-    for i in input {
-        match i {
-            token if token.is_number() || token.is_identifier() => output_queue.push(token),
-            token if token.is_operator() => {
-                loop {
-                    if operator_stack.is_empty() {
-                        break;
-                    }
+    Some((op, precedence * 2, precedence * 2 + 1))
+}
 
-                    // TODO: remove clone if possible
-                    let op = operator_stack[0].clone();
+/// How strongly assignments bind, they are at level 0 and right associative: `a = b = c` is
+/// `a = (b = c)`.
+const ASSIGN_BP: (u8, u8) = (1, 0);
 
-                    if op.precedence() > token.precedence() {
-                        break;
-                    }
+/// The assignment `kind` is. `Some(None)` for `=`, and the operator that is applied before
+/// assigning for compound assignments, `+=` is `Some(Some(OpKind::Add))`.
+fn assign_op(kind: &TokenKind) -> Option<Option<OpKind>> {
+    let op = match kind {
+        TokenKind::Eq => return Some(None),
+        TokenKind::PlusEq => OpKind::Add,
+        TokenKind::MinusEq => OpKind::Sub,
+        TokenKind::StarEq => OpKind::Mul,
+        TokenKind::SlashEq => OpKind::Div,
+        TokenKind::PercentEq => OpKind::Mod,
+        TokenKind::AndEq => OpKind::BitAnd,
+        TokenKind::PipeEq => OpKind::BitOr,
+        TokenKind::CaretEq => OpKind::BitXor,
+        TokenKind::ShiftLeftEq => OpKind::Shl,
+        TokenKind::ShiftRightEq => OpKind::Shr,
+        _ => return None,
+    };
 
-                    if op == Token::LeftParen {
-                        break;
-                    }
+    Some(Some(op))
+}
 
-                    operator_stack.pop();
-                    output_queue.push(op);
-                }
+/// Can an expression end before `kind`? These are the tokens that come after expressions in
+/// statements, `var a = b;`, `if (a)`, `for i in a..b {` and so on.
+fn ends_expression(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Semicolon
+            | TokenKind::RightParen
+            | TokenKind::LeftBracket
+            | TokenKind::RightBracket
+            | TokenKind::Comma
+            | TokenKind::DoubleDot
+            | TokenKind::DoubleDotEq
+            | TokenKind::Eof
+    )
+}
 
-                operator_stack.push(token);
-            }
-            Token::LeftParen => operator_stack.push(Token::LeftParen),
-            Token::RightParen => {
-                while *operator_stack
-                    .last()
-                    .expect("somehow our queue is too small")
-                    != Token::LeftParen
-                    && !operator_stack.is_empty()
-                {
-                    let op = operator_stack.pop().unwrap();
-                    output_queue.push(op);
-                }
+/// Parses an expression. It ends at the first token that can't continue it, which isn't
+/// consumed.
+pub fn parse_expression(p: &mut Parser) -> PResult<Expr> {
+    parse_binary(p, 0)
+}
 
-                operator_stack.pop();
+/// Parses an expression made of operators that bind at least as strongly as `min_bp`.
+fn parse_binary(p: &mut Parser, min_bp: u8) -> PResult<Expr> {
+    let mut left = parse_unary(p)?;
+
+    loop {
+        if let Some(op) = assign_op(&p.token.kind) {
+            if ASSIGN_BP.0 < min_bp {
+                break;
             }
-            _ => panic!("huh? Dump: {:?}", i),
+
+            left = parse_assignment(p, op, left)?;
+            continue;
+        }
+
+        let Some((op, left_bp, right_bp)) = binary_op(&p.token.kind) else {
+            break;
+        };
+
+        if left_bp < min_bp {
+            break;
         }
+
+        let op_span = p.token.span;
+
+        p.advance();
+        expect_operand(p, op_span)?;
+
+        let right = parse_binary(p, right_bp)?;
+        let span = left.span.to(right.span);
+
+        if let OpKind::And | OpKind::Or = op {
+            left = logical(op, left, right, span)?;
+            continue;
+        }
+
+        let kind = match apply(&op, &left.kind, &right.kind) {
+            Ok(Some(folded)) => folded,
+            Ok(None) => ExprKind::Binary(op, Box::new(left), Box::new(right)),
+            Err(e) => return Err(e.into_diagnostic(span)),
+        };
+
+        left = Expr { kind, span };
     }
 
-    while !operator_stack.is_empty() {
-        let op = operator_stack.pop().unwrap();
-        output_queue.push(op);
+    Ok(left)
+}
+
+/// Parses the value of `target = value` or a compound assignment like `target += value`. The
+/// "cursor" is at the `=`.
+fn parse_assignment(p: &mut Parser, op: Option<OpKind>, target: Expr) -> PResult<Expr> {
+    let op_span = p.token.span;
+    let symbol = match &op {
+        Some(op) => format!("{}=", op),
+        None => "=".to_string(),
+    };
+
+    if !matches!(target.kind, ExprKind::Ident(_)) {
+        return Err(not_assignable(&symbol, target.span, op_span));
     }
 
-    output_queue
+    p.advance();
+    expect_operand(p, op_span)?;
+
+    let value = parse_binary(p, ASSIGN_BP.1)?;
+    let span = target.span.to(value.span);
+
+    Ok(Expr {
+        kind: ExprKind::Assign(op, Box::new(target), Box::new(value)),
+        span,
+    })
+}
+
+/// `op` changes the value of `target`, but it isn't a variable.
+fn not_assignable(op: &str, target: Span, op_span: Span) -> Diagnostic {
+    Diagnostic::error(format!("`{}` needs a variable", op))
+        .with_code(ErrorCode::NotAssignable)
+        .with_label(target, "this can't be changed")
+        .with_secondary(op_span, "changes the value of this")
 }
 
-fn pack(tokens: Vec<Token>) -> Expression {
-    // TODO: make work with booleans
-    let mut values = Vec::new();
+/// Creates `left && right` or `left || right`. If `left` is a constant, it's folded the way it
+/// would be evaluated: `right` is dropped if it wouldn't be evaluated, and `left` if only
+/// `right` decides the result.
+fn logical(op: OpKind, left: Expr, right: Expr, span: Span) -> PResult<Expr> {
+    expect_bool(&op, &left)?;
+    expect_bool(&op, &right)?;
 
-    for token in tokens {
-        match token {
-            tok if tok.is_number() => values.push(tok),
-            tok if tok.is_boolean() => values.push(tok),
-            token if token.is_unary() => {
-                if let (Some(val0), Some(val1)) = (values.get(0), values.get(1)) {
-                    if let Token::Operator(ref op) = token {
-                        let res = apply(val0, val1, op);
+    let kind = match (&op, &left.kind) {
+        (OpKind::And, ExprKind::Bool(false)) | (OpKind::Or, ExprKind::Bool(true)) => left.kind,
+        (OpKind::And, ExprKind::Bool(true)) | (OpKind::Or, ExprKind::Bool(false)) => right.kind,
+        _ => ExprKind::Binary(op, Box::new(left), Box::new(right)),
+    };
 
-                        values.pop();
-                        values.pop();
+    Ok(Expr { kind, span })
+}
+
+/// Errors if `operand` of `op` is something that is never a boolean. Variables and calls
+/// aren't known yet, so they are let through.
+fn expect_bool(op: &OpKind, operand: &Expr) -> PResult<()> {
+    let found = match &operand.kind {
+        ExprKind::Int(..) => "an integer",
+        ExprKind::Float(_) => "a float",
+        ExprKind::Str(_) => "a string",
+        ExprKind::Char(_) => "a char",
+        ExprKind::Binary(
+            OpKind::Add
+            | OpKind::Sub
+            | OpKind::Mul
+            | OpKind::Div
+            | OpKind::Mod
+            | OpKind::Shl
+            | OpKind::Shr
+            | OpKind::Pow,
+            ..,
+        ) => "a number",
+        ExprKind::Unary(op, _) if *op != UnaryOp::Not => "a number",
+        _ => return Ok(()),
+    };
+
+    Err(
+        Diagnostic::error(format!("`{}` needs booleans, found {}", op, found))
+            .with_code(ErrorCode::MismatchedTypes)
+            .with_label(operand.span, format!("this is {}, not a boolean", found))
+            .with_note("compare it to something to get a boolean, like `a != 0`"),
+    )
+}
 
-                        values.push(res);
-                    }
-                } else {
-                    panic!("not enough values on the stack")
+/// Parses prefix operators, the operand and postfix operators after it.
+fn parse_unary(p: &mut Parser) -> PResult<Expr> {
+    let start = p.token.span;
+
+    let mut expr = match prefix_op(&p.token.kind) {
+        Some(op) => {
+            p.advance();
+            expect_operand(p, start)?;
+
+            match parse_min_literal(p, &op, start)? {
+                Some(expr) => expr,
+                None => {
+                    let operand = parse_binary(p, PREFIX_BP)?;
+
+                    unary(op, operand, start)?
                 }
             }
-            token if token.is_binary() => {}
-            token if token.is_identifier() => values.push(token),
-            token => panic!("{:?}", token),
         }
+        None => parse_primary(p)?,
+    };
+
+    while let Some(op) = postfix_op(&p.token.kind) {
+        let op_span = p.token.span;
+
+        p.advance();
+
+        expr = unary(op, expr, op_span)?;
+    }
+
+    Ok(expr)
+}
+
+/// `-9223372036854775808` and `-2147483648i32`. The literal is one too large for its type on
+/// its own, so it's negated right away. `None` if it's not one of those.
+fn parse_min_literal(p: &mut Parser, op: &UnaryOp, minus: Span) -> PResult<Option<Expr>> {
+    let TokenKind::IntegerLiteral(value, ty) = p.token.kind.clone() else {
+        return Ok(None);
+    };
+
+    // `-a ** b` is `-(a ** b)`, so the literal isn't negated there.
+    if *op != UnaryOp::Neg
+        || value <= int_range(&ty).1 as u64
+        || p.peek() == Some(&TokenKind::DoubleStar)
+    {
+        return Ok(None);
     }
 
-    Expression {
-        expr: token_to_expr_token(&values[0]),
+    let span = minus.to(p.token.span);
+
+    p.advance();
+
+    match fit(0i64.checked_sub_unsigned(value), &ty) {
+        Ok(kind) => Ok(Some(Expr { kind, span })),
+        Err(e) => Err(e.into_diagnostic(span)),
     }
 }
 
-fn token_to_expr_token(token: &Token) -> ExprToken {
-    match token {
-        Token::Num(a) => ExprToken::Other(ExprKind::Num(*a)),
-        Token::Boolean(a) => ExprToken::Other(ExprKind::Boolean(*a)),
-        Token::Other(op, l, r) => match &**l {
-            // we know that r has to be an identifier,
-            // otherwise this would have returned already.
-            // (Both are numbers. If not, that's a bug in apply()).
-            Token::Num(a) => {
-                if let Token::Ident(b) = &**r {
-                    fill(op, ExprKind::Num(*a), ExprKind::Ident(b.to_string()))
-                } else {
-                    panic!("not accepted {:?}", r)
-                }
-            }
-            Token::Ident(a) => match &**r {
-                Token::Ident(b) => fill(
-                    op,
-                    ExprKind::Ident(a.to_string()),
-                    ExprKind::Ident(b.to_string()),
-                ),
-                Token::Num(b) => fill(op, ExprKind::Ident(a.to_string()), ExprKind::Num(*b)),
-                _ => panic!("not accepted {:?}", r),
-            },
-            e => panic!("not accepted {:?}", e),
-        },
-        _ => panic!("not accepted {:?}", token),
-    }
-}
-
-/// Create an ExprToken from an unary operator `op` filled with `a` and `b`.
-fn fill(op: &OpKind, a: ExprKind, b: ExprKind) -> ExprToken {
-    match *op {
-        OpKind::Add => ExprToken::Add(a, b),
-        OpKind::Sub => ExprToken::Sub(a, b),
-        OpKind::Div => ExprToken::Div(a, b),
-        OpKind::Mul => ExprToken::Mul(a, b),
-        OpKind::Mod => ExprToken::Mod(a, b),
-        OpKind::BitXor => ExprToken::BitXor(a, b),
-        OpKind::BitOr => ExprToken::BitOr(a, b),
-        OpKind::BitAnd => ExprToken::BitAnd(a, b),
-        OpKind::Equals => ExprToken::Equals(a, b),
-        OpKind::NotEquals => ExprToken::NotEquals(a, b),
-        OpKind::GtEq => ExprToken::GtEq(a, b),
-        OpKind::LtEq => ExprToken::LtEq(a, b),
-        OpKind::Gt => ExprToken::Gt(a, b),
-        OpKind::Lt => ExprToken::Lt(a, b),
-        _ => panic!("not accepted {:?}", op),
-    }
-}
-
-/// Create an ExprToken from an binnary operator `op` filled with `a`.
-fn fill_binary(op: &OpKind, a: ExprKind) -> ExprToken {
-    match *op {
-        OpKind::Inc => ExprToken::Inc(a),
-        OpKind::Dec => ExprToken::Dec(a),
-        _ => panic!("not accepted {:?}", op),
-    }
-}
-
-fn apply(l: &Token, r: &Token, op: &OpKind) -> Token {
-    // TODO: get rid of clones
-    match l {
-        Token::Ident(ref a) => match r {
-            Token::Num(ref b) => Token::Other(
-                op.clone(),
-                box Token::Ident(a.to_string()),
-                box Token::Num(*b),
-            ),
-            Token::Ident(ref b) => Token::Other(
-                op.clone(),
-                box Token::Ident(a.to_string()),
-                box Token::Ident(b.to_string()),
-            ),
-            _ => panic!("not accepted {:?}", r),
-        },
-        Token::Num(a) => match r {
-            Token::Num(b) => match op {
-                OpKind::Add => Token::Num(a + b),
-                OpKind::Sub => Token::Num(a - b),
-                OpKind::Mul => Token::Num(a * b),
-                OpKind::Div => Token::Num(a / b),
-                OpKind::Mod => Token::Num(a % b),
-                OpKind::BitXor => Token::Num(a ^ b),
-                OpKind::BitOr => Token::Num(a | b),
-                OpKind::BitAnd => Token::Num(a & b),
-                OpKind::Equals => Token::Boolean(a == b),
-                OpKind::NotEquals => Token::Boolean(a != b),
-                OpKind::GtEq => Token::Boolean(a >= b),
-                OpKind::LtEq => Token::Boolean(a <= b),
-                OpKind::Gt => Token::Boolean(a > b),
-                OpKind::Lt => Token::Boolean(a < b),
-                _ => panic!("not accepted {:?}", op),
-            },
-            Token::Ident(b) => Token::Other(
-                op.clone(),
-                box Token::Num(*a),
-                box Token::Ident(b.to_string()),
-            ),
-            _ => panic!("not accepted {:?}", r),
+/// Creates `op operand`, or folds it if the operand is a constant. `op_span` is where the
+/// operator is.
+fn unary(op: UnaryOp, operand: Expr, op_span: Span) -> PResult<Expr> {
+    let span = if op_span.offset < operand.span.offset {
+        op_span.to(operand.span)
+    } else {
+        operand.span.to(op_span)
+    };
+
+    if op.is_increment() && !matches!(operand.kind, ExprKind::Ident(_)) {
+        return Err(not_assignable(&op.to_string(), operand.span, op_span));
+    }
+
+    let kind = match apply_unary(&op, &operand.kind) {
+        Ok(Some(folded)) => folded,
+        Ok(None) => ExprKind::Unary(op, Box::new(operand)),
+        Err(e) => return Err(e.into_diagnostic(span)),
+    };
+
+    Ok(Expr { kind, span })
+}
+
+/// Every operator needs something after it.
+fn expect_operand(p: &Parser, op_span: Span) -> PResult<()> {
+    if ends_expression(&p.token.kind) {
+        return Err(Diagnostic::error("an operator is missing an operand")
+            .with_code(ErrorCode::MissingOperand)
+            .with_label(op_span, "expected a value after this"));
+    }
+
+    Ok(())
+}
+
+/// Parses a literal, an identifier, a function call or an expression in parentheses.
+fn parse_primary(p: &mut Parser) -> PResult<Expr> {
+    let start = p.token.span;
+
+    let kind = match p.token.kind.clone() {
+        TokenKind::IntegerLiteral(a, ty) => match i64::try_from(a) {
+            Ok(a) if a <= int_range(&ty).1 => ExprKind::Int(a, ty),
+            _ => return Err(literal_too_large(&ty, start)),
         },
-        _ => panic!("not accepted {:?}", l),
+        TokenKind::FloatLiteral(a) => ExprKind::Float(a),
+        TokenKind::StringLiteral(a) => ExprKind::Str(a.into_owned()),
+        TokenKind::CharLiteral(a) => ExprKind::Char(a),
+        TokenKind::True => ExprKind::Bool(true),
+        TokenKind::False => ExprKind::Bool(false),
+        TokenKind::Unknown(_) if p.peek() == Some(&TokenKind::LeftParen) => {
+            return functions::parse_function_call(p)
+        }
+        TokenKind::Unknown(a) => ExprKind::Ident(a.to_string()),
+        TokenKind::LeftParen => {
+            p.advance();
+
+            let expr = parse_expression(p)?;
+
+            if p.token.kind != TokenKind::RightParen {
+                return Err(Diagnostic::error("unbalanced parentheses")
+                    .with_code(ErrorCode::UnbalancedParens)
+                    .with_label(
+                        p.token.span,
+                        format!("expected `)`, found {}", p.token.kind),
+                    )
+                    .with_secondary(start, "this `(` is never closed"));
+            }
+
+            p.advance();
+
+            // The parentheses are part of it, so errors about it point at them too.
+            return Ok(Expr {
+                kind: expr.kind,
+                span: start.to(p.prev_span()),
+            });
+        }
+        ref kind if ends_expression(kind) => return Err(p.unexpected("an expression")),
+        kind => {
+            return Err(
+                Diagnostic::error(format!("{} can't be used in an expression", kind))
+                    .with_code(ErrorCode::InvalidExpressionToken)
+                    .with_label(start, "not allowed here"),
+            )
+        }
+    };
+
+    p.advance();
+
+    Ok(Expr { kind, span: start })
+}
+
+/// Errors from folding constants. apply() doesn't know where the operands are, so
+/// parse_binary() turns them into diagnostics.
+#[derive(Debug, Clone, PartialEq)]
+enum ExprError {
+    DivisionByZero,
+    /// The result doesn't fit in the type.
+    Overflow(Type),
+    NegativeShift,
+    /// Integers with different type suffixes, `1u8 + 1i32`.
+    MismatchedTypes(Type, Type),
+}
+
+impl ExprError {
+    fn into_diagnostic(self, span: Span) -> Diagnostic {
+        match self {
+            ExprError::DivisionByZero => Diagnostic::error("division by zero")
+                .with_code(ErrorCode::DivisionByZero)
+                .with_label(span, "this always divides by zero"),
+            ExprError::Overflow(Type::None) => Diagnostic::error("arithmetic overflow")
+                .with_code(ErrorCode::ConstantOverflow)
+                .with_label(span, "this doesn't fit in 64 bits"),
+            ExprError::Overflow(ty) => {
+                let (min, max) = int_range(&ty);
+
+                Diagnostic::error("arithmetic overflow")
+                    .with_code(ErrorCode::ConstantOverflow)
+                    .with_label(span, format!("this doesn't fit in `{}`", ty))
+                    .with_note(format!("`{}` goes from {} to {}", ty, min, max))
+            }
+            ExprError::NegativeShift => Diagnostic::error("shift by a negative amount")
+                .with_code(ErrorCode::NegativeShift)
+                .with_label(span, "this shifts by less than 0 bits")
+                .with_note("use the other shift operator to shift the other way"),
+            ExprError::MismatchedTypes(l, r) => {
+                Diagnostic::error(format!("mismatched integer types `{}` and `{}`", l, r))
+                    .with_code(ErrorCode::MismatchedTypes)
+                    .with_label(span, "both sides need to have the same type")
+            }
+        }
+    }
+}
+
+/// The smallest and the largest value of an integer type. Integers without a suffix are 64
+/// bits.
+fn int_range(ty: &Type) -> (i64, i64) {
+    match ty {
+        Type::I32 => (i32::MIN as i64, i32::MAX as i64),
+        Type::U32 => (0, u32::MAX as i64),
+        Type::U8 => (0, u8::MAX as i64),
+        _ => (i64::MIN, i64::MAX),
+    }
+}
+
+/// How many bits an integer type has.
+fn int_bits(ty: &Type) -> i64 {
+    match ty {
+        Type::I32 | Type::U32 => 32,
+        Type::U8 => 8,
+        _ => 64,
+    }
+}
+
+/// Drops the bits of `value` that don't fit in `ty`, the way they are dropped at run time.
+fn wrap(value: i64, ty: &Type) -> i64 {
+    match ty {
+        Type::I32 => value as i32 as i64,
+        Type::U32 => value as u32 as i64,
+        Type::U8 => value as u8 as i64,
+        _ => value,
+    }
+}
+
+/// The lexer lets `i64::MAX + 1` and `i32::MAX + 1` through for `-9223372036854775808` and
+/// `-2147483648i32`, anywhere else they're too large.
+fn literal_too_large(ty: &Type, span: Span) -> Diagnostic {
+    let max = int_range(ty).1;
+    let diagnostic = match ty {
+        Type::None => Diagnostic::error("integer literal is too large")
+            .with_label(span, format!("the largest integer is {}", max)),
+        _ => Diagnostic::error(format!("literal out of range for `{}`", ty))
+            .with_label(span, format!("the largest `{}` is {}", ty, max)),
+    };
+
+    diagnostic
+        .with_code(ErrorCode::IntegerOverflow)
+        .with_note(format!(
+            "`-{}` fits, because the negative numbers go one further",
+            max as u64 + 1
+        ))
+}
+
+/// An integer of type `ty`, or an error if `value` overflowed or doesn't fit in `ty`.
+fn fit(value: Option<i64>, ty: &Type) -> Result<ExprKind, ExprError> {
+    let (min, max) = int_range(ty);
+
+    match value {
+        Some(value) if (min..=max).contains(&value) => Ok(ExprKind::Int(value, ty.clone())),
+        _ => Err(ExprError::Overflow(ty.clone())),
     }
 }
 
-pub fn parse_expression(expr: Vec<lexer::Token>) -> Expression {
-    let tokens = tokenize(expr);
-    let postfix = convert(tokens);
-    pack(postfix)
+/// Folds `l op r` if both of them are constants. `None` if it can't be folded.
+///
+/// The result has the type of the suffixed side, `1 + 2u8` is `3u8`. Both sides need the same
+/// suffix if they both have one, except for the right side of shifts and `**`.
+fn apply(op: &OpKind, l: &ExprKind, r: &ExprKind) -> Result<Option<ExprKind>, ExprError> {
+    let (a, b, l_ty, r_ty) = match (l, r) {
+        (ExprKind::Int(a, l_ty), ExprKind::Int(b, r_ty)) => (*a, *b, l_ty, r_ty),
+        _ => return Ok(None),
+    };
+
+    let ty = match (op, l_ty, r_ty) {
+        (OpKind::Shl | OpKind::Shr | OpKind::Pow, _, _) => l_ty,
+        (_, Type::None, ty) | (_, ty, Type::None) => ty,
+        (_, l_ty, r_ty) if l_ty == r_ty => l_ty,
+        _ => return Err(ExprError::MismatchedTypes(l_ty.clone(), r_ty.clone())),
+    };
+
+    let folded = match op {
+        OpKind::Add => fit(a.checked_add(b), ty)?,
+        OpKind::Sub => fit(a.checked_sub(b), ty)?,
+        OpKind::Mul => fit(a.checked_mul(b), ty)?,
+        OpKind::Div | OpKind::Mod if b == 0 => return Err(ExprError::DivisionByZero),
+        OpKind::Div => fit(a.checked_div(b), ty)?,
+        OpKind::Mod => fit(a.checked_rem(b), ty)?,
+        OpKind::BitXor => fit(Some(a ^ b), ty)?,
+        OpKind::BitOr => fit(Some(a | b), ty)?,
+        OpKind::BitAnd => fit(Some(a & b), ty)?,
+        OpKind::Equals => ExprKind::Bool(a == b),
+        OpKind::NotEquals => ExprKind::Bool(a != b),
+        OpKind::GtEq => ExprKind::Bool(a >= b),
+        OpKind::LtEq => ExprKind::Bool(a <= b),
+        OpKind::Gt => ExprKind::Bool(a > b),
+        OpKind::Lt => ExprKind::Bool(a < b),
+        OpKind::Shl | OpKind::Shr if b < 0 => return Err(ExprError::NegativeShift),
+        // Bits shifted out are dropped, so shifting by the size of the type or more leaves
+        // nothing.
+        OpKind::Shl if b >= int_bits(ty) => ExprKind::Int(0, ty.clone()),
+        OpKind::Shl => ExprKind::Int(wrap(a << b, ty), ty.clone()),
+        // The sign is copied into the new bits, so it ends up 0 or -1.
+        OpKind::Shr => ExprKind::Int(a >> b.min(63), ty.clone()),
+        OpKind::Pow => fit(pow(a, b)?, ty)?,
+        // logical() takes care of these, integers are never their operands.
+        OpKind::And | OpKind::Or => return Ok(None),
+    };
+
+    Ok(Some(folded))
+}
+
+/// `a ** b`, `None` if it overflows. A negative exponent gives `1 / a ** -b`, which is 0 unless
+/// `a` is 1 or -1, the same as integer division.
+fn pow(a: i64, b: i64) -> Result<Option<i64>, ExprError> {
+    Ok(match (a, b) {
+        (0, ..=-1) => return Err(ExprError::DivisionByZero),
+        (1, _) => Some(1),
+        (-1, _) => Some(if b % 2 == 0 { 1 } else { -1 }),
+        (_, ..=-1) => Some(0),
+        (0, 1..) => Some(0),
+        _ => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+    })
+}
+
+/// Folds `op a` if `a` is a constant. `None` if it can't be folded.
+fn apply_unary(op: &UnaryOp, a: &ExprKind) -> Result<Option<ExprKind>, ExprError> {
+    let folded = match (op, a) {
+        (UnaryOp::Neg, ExprKind::Int(a, ty)) => fit(a.checked_neg(), ty)?,
+        (UnaryOp::Neg, ExprKind::Float(a)) => ExprKind::Float(-a),
+        (UnaryOp::BitNot, ExprKind::Int(a, ty)) => ExprKind::Int(wrap(!a, ty), ty.clone()),
+        (UnaryOp::Not, ExprKind::Bool(a)) => ExprKind::Bool(!a),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(folded))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::lexer;
+    use crate::lexer::Lexer;
+    use crate::span::FileId;
+
+    fn parse(src: &str) -> PResult<Expr> {
+        let mut p = Parser::new(Lexer::new(src, FileId(0)));
+        let expr = parse_expression(&mut p)?;
+
+        if p.token.kind != TokenKind::Eof {
+            return Err(p.unexpected("the end of the expression"));
+        }
+
+        Ok(expr)
+    }
+
+    /// Writes the tree with parentheses around every operation, `a + b * c` is
+    /// `(a + (b * c))`.
+    fn show(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Binary(op, l, r) => format!("({} {:?} {})", show(l), op, show(r)),
+            ExprKind::Unary(op, a) => format!("({:?} {})", op, show(a)),
+            ExprKind::Assign(None, a, b) => format!("({} = {})", show(a), show(b)),
+            ExprKind::Assign(Some(op), a, b) => format!("({} {:?}= {})", show(a), op, show(b)),
+            ExprKind::Int(a, Type::None) => a.to_string(),
+            ExprKind::Int(a, ty) => format!("{}{}", a, ty),
+            ExprKind::Bool(a) => a.to_string(),
+            ExprKind::Ident(a) => a.to_string(),
+            ExprKind::Call(call) => format!(
+                "{}({})",
+                call.name,
+                call.args
+                    .iter()
+                    .map(|a| show(&a.value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            kind => format!("{:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let cases = [
+            ("1", "1"),
+            ("abc", "abc"),
+            ("a + b", "(a Add b)"),
+            ("a * b + c * d", "((a Mul b) Add (c Mul d))"),
+            ("a + b * c - d", "((a Add (b Mul c)) Sub d)"),
+            ("a - b - c", "((a Sub b) Sub c)"),
+            ("a / b % c", "((a Div b) Mod c)"),
+            ("(a + b) * c", "((a Add b) Mul c)"),
+            ("a * (b + (c - d))", "(a Mul (b Add (c Sub d)))"),
+            ("a == b + 1", "(a Equals (b Add 1))"),
+            ("a < b == c >= d", "((a Lt b) Equals (c GtEq d))"),
+            ("a | b ^ c & d", "(a BitOr (b BitXor (c BitAnd d)))"),
+            ("a & b == c", "(a BitAnd (b Equals c))"),
+            ("a = b", "(a = b)"),
+            ("a = b = c", "(a = (b = c))"),
+            ("i = i + 1", "(i = (i Add 1))"),
+            ("a += b * 2", "(a Add= (b Mul 2))"),
+            ("a -= b -= c", "(a Sub= (b Sub= c))"),
+            ("a <<= 1", "(a Shl= 1)"),
+            ("a >>= b ^= c", "(a Shr= (b BitXor= c))"),
+            ("a %= b", "(a Mod= b)"),
+            ("a = b || c && d", "(a = (b Or (c And d)))"),
+            ("a = 1 + 2", "(a = 3)"),
+            ("f(a = b)", "f((a = b))"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_parse_expression_folds_constants() {
+        let cases = [
+            ("1 + 2", "3"),
+            ("12 / 3", "4"),
+            ("(1 + 2) * 3", "9"),
+            ("1 + 2 * 3 - 4", "3"),
+            ("1 == 2", "false"),
+            ("7 % 4 != 3", "false"),
+            ("a + 2 * 3", "(a Add 6)"),
+            // Only operations on two constants are folded.
+            ("1 + a + 2", "((1 Add a) Add 2)"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_parse_unary_operators() {
+        let cases = [
+            ("-x", "(Neg x)"),
+            ("!done", "(Not done)"),
+            ("~mask", "(BitNot mask)"),
+            ("i++", "(PostInc i)"),
+            ("--n", "(PreDec n)"),
+            ("++n", "(PreInc n)"),
+            ("n--", "(PostDec n)"),
+            ("-a * b", "((Neg a) Mul b)"),
+            ("a * -b", "(a Mul (Neg b))"),
+            ("a - -b", "(a Sub (Neg b))"),
+            ("-(a + b)", "(Neg (a Add b))"),
+            ("- -a", "(Neg (Neg a))"),
+            ("!a == b", "((Not a) Equals b)"),
+            ("-i++", "(Neg (PostInc i))"),
+            ("i++ + 1", "((PostInc i) Add 1)"),
+            ("~a & b", "((BitNot a) BitAnd b)"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_fold_unary_operators() {
+        let cases = [
+            ("-1", "-1"),
+            ("-(1 + 2) * 3", "-9"),
+            ("~0", "-1"),
+            ("!true", "false"),
+            ("!(1 == 2)", "true"),
+            ("2 - -2", "4"),
+            // `!` isn't bitwise not.
+            ("!1", "(Not 1)"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_parse_unary_operator_spans() {
+        let expr = parse("-a + b--").unwrap();
+
+        let ExprKind::Binary(_, l, r) = expr.kind else {
+            panic!("not a binary expression: {:?}", expr);
+        };
+
+        assert_eq!(l.span, Span::new(FileId(0), 0, 2, 1, 1));
+        assert_eq!(r.span, Span::new(FileId(0), 5, 3, 1, 6));
+    }
+    #[test]
+    fn test_parse_calls_and_literals() {
+        let cases = [
+            ("println(x + 1)", "println((x Add 1))"),
+            ("len(\"abc\") * 2", "(len(Str(\"abc\")) Mul 2)"),
+            ("f(g(1), -h())", "f(g(1), (Neg h()))"),
+            ("f()", "f()"),
+            ("f(a, b,)", "f(a, b)"),
+            ("-f(a) * 2", "((Neg f(a)) Mul 2)"),
+            ("f(a)++", "`++` needs a variable"),
+            ("1.5 * r", "(Float(1.5) Mul r)"),
+            ("c == 'a'", "(c Equals Char('a'))"),
+            // Only names can be called.
+            ("(f)(1)", "expected the end of the expression, found `(`"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = match parse(src) {
+                Ok(expr) => show(&expr),
+                Err(e) => e.message,
+            };
+
+            assert_eq!(expr, *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_parse_logical_operators() {
+        let cases = [
+            ("a && b", "(a And b)"),
+            ("a || b && c", "(a Or (b And c))"),
+            ("a && b || c", "((a And b) Or c)"),
+            ("a || b || c", "((a Or b) Or c)"),
+            ("a == 1 && b < c", "((a Equals 1) And (b Lt c))"),
+            ("!a || b | c", "((Not a) Or (b BitOr c))"),
+            ("f(a) && g()", "(f(a) And g())"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_fold_logical_operators_short_circuits() {
+        let cases = [
+            ("false && f()", "false"),
+            ("true || f()", "true"),
+            ("true && f()", "f()"),
+            ("false || a", "a"),
+            ("1 < 2 || f()", "true"),
+            ("1 > 2 && a || b", "b"),
+            // The left side is always evaluated, so it can't be dropped.
+            ("f() && false", "(f() And false)"),
+            ("a || true", "(a Or true)"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_logical_operators_need_booleans() {
+        let cases = [
+            ("1 && a", "`&&` needs booleans, found an integer"),
+            ("a || \"s\"", "`||` needs booleans, found a string"),
+            ("a && b + 1", "`&&` needs booleans, found a number"),
+            ("-a || b", "`||` needs booleans, found a number"),
+            ("false && 'c'", "`&&` needs booleans, found a char"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let e = parse(src).unwrap_err();
+
+            assert_eq!(e.code, Some(ErrorCode::MismatchedTypes));
+            assert_eq!(e.message, *expected, "Test case {} failed", i + 1);
+        }
+
+        let e = parse("a && b + 1").unwrap_err();
+
+        assert_eq!(e.primary_span(), Some(Span::new(FileId(0), 5, 5, 1, 6)));
+    }
+    #[test]
+    fn test_parse_shift_and_power() {
+        let cases = [
+            ("a << b", "(a Shl b)"),
+            // Shifts bind more strongly than `+`, unlike in C.
+            ("a >> b + c", "((a Shr b) Add c)"),
+            ("a * b << c", "((a Mul b) Shl c)"),
+            ("a << b < c", "((a Shl b) Lt c)"),
+            ("a << b >> c", "((a Shl b) Shr c)"),
+            ("a ** b", "(a Pow b)"),
+            ("a ** b ** c", "(a Pow (b Pow c))"),
+            ("a * b ** c", "(a Mul (b Pow c))"),
+            ("-a ** b", "(Neg (a Pow b))"),
+            ("a ** -b", "(a Pow (Neg b))"),
+            ("a ** b++", "(a Pow (PostInc b))"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_fold_shift_and_power() {
+        let cases = [
+            ("1 << 4", "16"),
+            ("-16 >> 2", "-4"),
+            ("1 << 63", "-9223372036854775808"),
+            ("3 << 63", "-9223372036854775808"),
+            ("1 << 64", "0"),
+            ("1 << 1000", "0"),
+            ("5 >> 64", "0"),
+            ("-5 >> 1000", "-1"),
+            ("2 ** 10", "1024"),
+            ("2 ** 3 ** 2", "512"),
+            ("-2 ** 2", "-4"),
+            ("(-2) ** 3", "-8"),
+            ("0 ** 0", "1"),
+            ("2 ** -1", "0"),
+            ("1 ** -5", "1"),
+            ("-1 ** -3", "-1"),
+            ("(-1) ** -3", "-1"),
+            ("(-1) ** -4", "1"),
+            ("1 ** 9223372036854775807", "1"),
+            ("0 ** 4294967296", "0"),
+            ("0 ** 9223372036854775807", "0"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_fold_shift_and_power_errors() {
+        let cases = [
+            ("1 << -1", ErrorCode::NegativeShift),
+            ("1 >> -64", ErrorCode::NegativeShift),
+            ("0 ** -1", ErrorCode::DivisionByZero),
+            ("2 ** 63", ErrorCode::ConstantOverflow),
+            ("10 ** 4294967296", ErrorCode::ConstantOverflow),
+            ("a && 1 << b", ErrorCode::MismatchedTypes),
+        ];
 
+        for (i, (src, code)) in cases.into_iter().enumerate() {
+            let e = parse(src).unwrap_err();
+
+            assert_eq!(e.code, Some(code), "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_fold_integers_with_suffixes() {
+        let cases = [
+            ("200u8 + 55u8", "255u8"),
+            ("1 + 2u8", "3u8"),
+            ("2u8 * 3", "6u8"),
+            ("~0u8", "255u8"),
+            ("~0u32", "4294967295u32"),
+            ("~0i32", "-1i32"),
+            ("1u8 << 7", "128u8"),
+            ("1u8 << 8", "0u8"),
+            ("3u8 << 7", "128u8"),
+            ("1i32 << 31", "-2147483648i32"),
+            ("255u8 >> 100", "0u8"),
+            ("1u8 << 2i32", "4u8"),
+            ("2u8 ** 7", "128u8"),
+            ("-5i32", "-5i32"),
+            ("-2147483648i32", "-2147483648i32"),
+            ("-9223372036854775808", "-9223372036854775808"),
+            ("-0x8000_0000_0000_0000", "-9223372036854775808"),
+            ("1u8 == 1", "true"),
+            ("200u8 + a", "(200u8 Add a)"),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let expr = parse(src).unwrap();
+
+            assert_eq!(show(&expr), *expected, "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_fold_integers_with_suffixes_errors() {
+        let cases = [
+            ("200u8 + 100u8", ErrorCode::ConstantOverflow),
+            ("0u8 - 1", ErrorCode::ConstantOverflow),
+            ("-1u8", ErrorCode::ConstantOverflow),
+            ("-1u32", ErrorCode::ConstantOverflow),
+            ("2147483647i32 + 1", ErrorCode::ConstantOverflow),
+            ("65536u32 * 65536u32", ErrorCode::ConstantOverflow),
+            ("2u8 ** 8", ErrorCode::ConstantOverflow),
+            ("1u8 + 1i32", ErrorCode::MismatchedTypes),
+            ("1u8 == 1u32", ErrorCode::MismatchedTypes),
+            ("9223372036854775808", ErrorCode::IntegerOverflow),
+            ("2147483648i32", ErrorCode::IntegerOverflow),
+            ("-(9223372036854775808)", ErrorCode::IntegerOverflow),
+            ("-9223372036854775808 ** 1", ErrorCode::IntegerOverflow),
+            ("-(-9223372036854775808)", ErrorCode::ConstantOverflow),
+        ];
+
+        for (i, (src, code)) in cases.into_iter().enumerate() {
+            let e = parse(src).unwrap_err();
+
+            assert_eq!(e.code, Some(code), "Test case {} failed", i + 1);
+        }
+
+        let e = parse("200u8 + 100u8").unwrap_err();
+
+        assert_eq!(e.labels[0].message, "this doesn't fit in `u8`");
+        assert_eq!(e.notes, vec!["`u8` goes from 0 to 255"]);
+    }
     #[test]
-    fn test_tokenize() {
-        let i0 = lexer("1 + 2");
-        let i1 = lexer("12 / 3");
-        let i2 = lexer("(1 + 2) * 3");
-        let i3 = lexer("abc / 3");
-        let i4 = lexer("1 == 2");
-
-        let t0 = format!("{:?}", tokenize(i0));
-        let t1 = format!("{:?}", tokenize(i1));
-        let t2 = format!("{:?}", tokenize(i2));
-        let t3 = format!("{:?}", tokenize(i3));
-        let t4 = format!("{:?}", tokenize(i4));
-
-        let e0 = "[Num(1), Operator(Add), Num(2)]".to_string();
-        let e1 = "[Num(12), Operator(Div), Num(3)]".to_string();
-        let e2 = "[LeftParen, Num(1), Operator(Add), Num(2), RightParen, Operator(Mul), Num(3)]"
-            .to_string();
-        let e3 = "[Ident(\"abc\"), Operator(Div), Num(3)]".to_string();
-        let e4 = "[Num(1), Operator(Equals), Num(2)]".to_string();
-
-        assert_eq!(t0, e0, "Test case 1 failed");
-        assert_eq!(t1, e1, "Test case 2 failed");
-        assert_eq!(t2, e2, "Test case 3 failed");
-        assert_eq!(t3, e3, "Test case 4 failed");
-        assert_eq!(t4, e4, "Test case 5 failed");
+    fn test_parse_expression_span() {
+        let expr = parse("  abc + 4").unwrap();
+
+        assert_eq!(expr.span, Span::new(FileId(0), 2, 7, 1, 3));
+    }
+    #[test]
+    fn test_parse_expression_builds_a_tree() {
+        let expr = parse("abc != (3)").unwrap();
+
+        assert_eq!(
+            expr,
+            Expr {
+                kind: ExprKind::Binary(
+                    OpKind::NotEquals,
+                    Box::new(Expr {
+                        kind: ExprKind::Ident("abc".to_string()),
+                        span: Span::new(FileId(0), 0, 3, 1, 1)
+                    }),
+                    Box::new(Expr {
+                        kind: ExprKind::Int(3, Type::None),
+                        span: Span::new(FileId(0), 7, 3, 1, 8)
+                    })
+                ),
+                span: Span::new(FileId(0), 0, 10, 1, 1)
+            }
+        );
     }
     #[test]
-    fn test_convert() {
-        let i0 = lexer("1 + 2");
-        let i1 = lexer("12 / 3");
-        let i2 = lexer("(1 + 2) * 3");
-        let i3 = lexer("abc / 3");
-        let i4 = lexer("1 == 2");
-
-        let o0 = format!("{:?}", convert(tokenize(i0)));
-        let o1 = format!("{:?}", convert(tokenize(i1)));
-        let o2 = format!("{:?}", convert(tokenize(i2)));
-        let o3 = format!("{:?}", convert(tokenize(i3)));
-        let o4 = format!("{:?}", convert(tokenize(i4)));
-
-        let e0 = "[Num(1), Num(2), Operator(Add)]".to_string();
-        let e1 = "[Num(12), Num(3), Operator(Div)]".to_string();
-        let e2 = "[Num(1), Num(2), Operator(Add), Num(3), Operator(Mul)]".to_string();
-        let e3 = "[Ident(\"abc\"), Num(3), Operator(Div)]".to_string();
-        let e4 = "[Num(1), Num(2), Operator(Equals)]".to_string();
-
-        assert_eq!(o0, e0, "Test case 1 failed");
-        assert_eq!(o1, e1, "Test case 2 failed");
-        assert_eq!(o2, e2, "Test case 3 failed");
-        assert_eq!(o3, e3, "Test case 4 failed");
-        assert_eq!(o4, e4, "Test case 5 failed");
+    fn test_parse_expression_stops_at_the_end() {
+        let mut p = Parser::new(Lexer::new("a + 1; b", FileId(0)));
+        let expr = parse_expression(&mut p).unwrap();
+
+        assert_eq!(show(&expr), "(a Add 1)");
+        assert_eq!(p.token.kind, TokenKind::Semicolon);
     }
     #[test]
-    fn test_pack() {
-        let i0 = lexer("1 + 2");
-        let i1 = lexer("12 + 3");
-        let i2 = lexer("(1 + 2) * 3");
-        let i3 = lexer("abc + 4");
-        let i4 = lexer("4 + abc");
-        let i5 = lexer("1 == 2");
-        let i6 = lexer("abc != 3");
-
-        let o0 = format!("{:?}", pack(convert(tokenize(i0))));
-        let o1 = format!("{:?}", pack(convert(tokenize(i1))));
-        let o2 = format!("{:?}", pack(convert(tokenize(i2))));
-        let o3 = format!("{:?}", pack(convert(tokenize(i3))));
-        let o4 = format!("{:?}", pack(convert(tokenize(i4))));
-        let o5 = format!("{:?}", pack(convert(tokenize(i5))));
-        let o6 = format!("{:?}", pack(convert(tokenize(i6))));
-
-        let e0 = "Expression { expr: Other(Num(3)) }".to_string();
-        let e1 = "Expression { expr: Other(Num(15)) }".to_string();
-        let e2 = "Expression { expr: Other(Num(9)) }".to_string();
-        let e3 = "Expression { expr: Add(Ident(\"abc\"), Num(4)) }".to_string();
-        let e4 = "Expression { expr: Add(Num(4), Ident(\"abc\")) }".to_string();
-        let e5 = "Expression { expr: Other(Boolean(false)) }".to_string();
-        let e6 = "Expression { expr: NotEquals(Ident(\"abc\"), Num(3)) }".to_string();
-
-        assert_eq!(o0, e0, "Test case 1 failed");
-        assert_eq!(o1, e1, "Test case 2 failed");
-        assert_eq!(o2, e2, "Test case 3 failed");
-        assert_eq!(o3, e3, "Test case 4 failed");
-        assert_eq!(o4, e4, "Test case 5 failed");
-        assert_eq!(o5, e5, "Test case 6 failed");
-        assert_eq!(o6, e6, "Test case 7 failed");
+    fn test_parse_expression_errors() {
+        let cases = [
+            ("1 / 0", ErrorCode::DivisionByZero),
+            ("a + 1 % (2 - 2)", ErrorCode::DivisionByZero),
+            ("(1 + 2", ErrorCode::UnbalancedParens),
+            ("((1 + 2) b", ErrorCode::UnbalancedParens),
+            ("1 + 2)", ErrorCode::UnexpectedToken),
+            ("1 +", ErrorCode::MissingOperand),
+            ("1 + ;", ErrorCode::MissingOperand),
+            ("1 + fn", ErrorCode::InvalidExpressionToken),
+            (";", ErrorCode::UnexpectedToken),
+            ("-", ErrorCode::MissingOperand),
+            ("a * !", ErrorCode::MissingOperand),
+            ("++1", ErrorCode::NotAssignable),
+            ("(a + b)--", ErrorCode::NotAssignable),
+            ("++a++", ErrorCode::NotAssignable),
+            ("1 = a", ErrorCode::NotAssignable),
+            ("a + b = c", ErrorCode::NotAssignable),
+            ("f() += 1", ErrorCode::NotAssignable),
+            ("a = b + 1 = c", ErrorCode::NotAssignable),
+            ("a =", ErrorCode::MissingOperand),
+            ("a *= ;", ErrorCode::MissingOperand),
+            ("9223372036854775807 + 1", ErrorCode::ConstantOverflow),
+        ];
+
+        for (i, (src, code)) in cases.into_iter().enumerate() {
+            let e = parse(src).unwrap_err();
+
+            assert_eq!(e.code, Some(code), "Test case {} failed", i + 1);
+        }
+    }
+    #[test]
+    fn test_parse_expression_error_spans() {
+        let e = parse("a * (1 / 0)").unwrap_err();
+
+        assert_eq!(e.primary_span(), Some(Span::new(FileId(0), 5, 5, 1, 6)));
+
+        let e = parse("(a + 1").unwrap_err();
+
+        assert_eq!(e.labels[1].span, Span::new(FileId(0), 0, 1, 1, 1));
     }
 }
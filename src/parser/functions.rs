@@ -1,292 +1,336 @@
+use super::ast::{Arg, Call, Expr, ExprKind, FnDecl, Param};
+use super::expressions::parse_expression;
+use super::statements::parse_block;
 use super::*;
 use crate::lexer::*;
 
-/// Arguments passed to a function. (name, modifiers, type)
-pub type Parameters = Vec<(String, Vec<Modifiers>, Type)>;
-pub type CallParameters = Vec<(String, Vec<Modifiers>)>;
-
-/// Parses a function and appends the token to the list.
-///
-/// # Panics
-///
-/// Panics if there was a mistake in the syntax.
-pub fn parse_function(p: &mut Parser) {
+/// Parses a function.
+pub fn parse_function(p: &mut Parser) -> PResult<FnDecl> {
     // TODO: remove all the clones
-    let name;
+    let doc = p.take_docs();
+    let start = p.token.span;
 
     // keyword and name
-    if p.tokens[p.i].kind == TokenKind::Fn
-        && p.tokens[p.i + 1].kind == TokenKind::Unknown(String::from(""))
-    {
-        match p.tokens[p.i + 1].kind.clone() {
-            TokenKind::Unknown(a) => {
-                p.advance_with(2);
-                name = a
-            }
-            _ => unreachable!(),
-        }
-    } else {
-        panic!("syntax error or idk: {:#?}, {:?}", p, p.tokens[p.i].kind)
+    if p.token.kind != TokenKind::Fn {
+        return Err(p.unexpected("`fn`"));
     }
 
-    if p.tokens[p.i].kind != TokenKind::LeftParen {
-        panic!("syntax error or idk: {:#?}, {:?}", p, p.tokens[p.i].kind)
+    p.advance();
+
+    let name = match p.token.kind.clone() {
+        TokenKind::Unknown(a) => {
+            p.advance();
+            a.to_string()
+        }
+        _ => return Err(p.unexpected("a function name")),
+    };
+
+    if p.token.kind != TokenKind::LeftParen {
+        return Err(p.unexpected("`(`"));
     }
 
     p.advance();
 
     let mut parameters = Vec::new();
-    let mut param_name;
 
     loop {
+        let before = p.token.span;
         let mut param_modifiers = Vec::new();
         let mut param_type = Type::None; // dis
 
         // if it's a identifier
-        if p.tokens[p.i].kind == TokenKind::Unknown(String::from("")) {
-            match p.tokens[p.i].kind.clone() {
-                TokenKind::Unknown(a) => {
-                    p.advance();
-
-                    param_name = a
-                }
-                _ => unreachable!(),
-            }
+        if let TokenKind::Unknown(param_name) = p.token.kind.clone() {
+            let param_start = p.token.span;
+
+            p.advance();
 
             // Modifiers
-            if p.tokens[p.i].kind == TokenKind::Mut {
-                param_modifiers.push(Modifiers::Mutable)
+            if p.token.kind == TokenKind::Mut {
+                param_modifiers.push(Modifiers::Mutable);
+                p.advance();
             }
 
-            if p.tokens[p.i].kind == TokenKind::Const {
-                param_modifiers.push(Modifiers::Constant)
+            if p.token.kind == TokenKind::Const {
+                param_modifiers.push(Modifiers::Constant);
+                p.advance();
             }
 
             // Param type
-            if p.tokens[p.i].kind == TokenKind::Type(Type::None) {
-                match p.tokens[p.i].kind.clone() {
-                    TokenKind::Type(a) => {
-                        p.advance();
-
-                        param_type = a;
-                    }
-                    _ => panic!("syntax error or something: {:#?}", p),
-                }
-            }
-
-            if p.tokens[p.i].kind == TokenKind::Comma {
+            if let TokenKind::Type(a) = p.token.kind.clone() {
                 p.advance();
+
+                param_type = a;
             }
 
-            parameters.push((param_name, param_modifiers, param_type));
+            parameters.push(Param {
+                name: param_name.to_string(),
+                modifiers: param_modifiers,
+                ty: param_type,
+                span: param_start.to(p.prev_span()),
+            });
+
+            // Parameters are separated with commas.
+            match p.token.kind {
+                TokenKind::Comma => p.advance(),
+                TokenKind::RightParen => {}
+                _ => return Err(p.unexpected("`,` or `)`")),
+            }
         }
 
-        if p.tokens[p.i].kind == TokenKind::RightParen {
+        if p.token.kind == TokenKind::RightParen {
             p.advance();
             break;
         }
+
+        // Nothing was consumed, so it can't be a parameter.
+        if p.token.span == before {
+            return Err(p.unexpected("a parameter name or `)`"));
+        }
     }
 
-    let return_type = match p.tokens[p.i].kind.clone() {
-        TokenKind::Type(a) => a,
+    let return_type = match p.token.kind.clone() {
+        TokenKind::Type(a) => {
+            p.advance();
+            a
+        }
         _ => Type::Void,
     };
 
-    p.add_token(ParsedToken::Function(name, parameters, return_type));
-}
-
-/// Parses a function call and appends the token to the list.
-pub fn parse_function_call(p: &mut Parser) {
-    // TODO: remove all the clones
+    // `fn foo() i32;` only declares the function, it's defined somewhere else.
+    let body = if p.token.kind == TokenKind::Semicolon {
+        p.advance();
+        None
+    } else {
+        Some(parse_block(p)?)
+    };
 
-    // Identifier
+    Ok(FnDecl {
+        doc,
+        name,
+        params: parameters,
+        return_type,
+        body,
+        span: start.to(p.prev_span()),
+    })
+}
 
-    let name = match p.tokens[p.i].kind.clone() {
-        TokenKind::Unknown(a) => a,
-        _ => panic!("syntax error"),
+/// Parses a function call, `name(arg, mut arg, ...)`. The arguments can be any expression.
+pub fn parse_function_call(p: &mut Parser) -> PResult<Expr> {
+    let start = p.token.span;
+    let name = match p.token.kind.clone() {
+        TokenKind::Unknown(a) => a.to_string(),
+        _ => return Err(p.unexpected("a function name")),
     };
 
     p.advance();
 
-    if p.tokens[p.i].kind == TokenKind::LeftParen {
-        p.advance();
-    } else {
-        panic!("syntax error")
+    if p.token.kind != TokenKind::LeftParen {
+        return Err(p.unexpected("`(`"));
     }
 
-    let mut parameters = Vec::new();
-    let mut parameter_name;
+    let open = p.token.span;
 
-    // parameters
-    loop {
-        let mut parameter_modifiers = Vec::new();
+    p.advance();
 
-        // Modifiers
-        if p.tokens[p.i].kind == TokenKind::Mut {
-            parameter_modifiers.push(Modifiers::Mutable);
-            p.advance();
-        }
+    let mut args = Vec::new();
+
+    while p.token.kind != TokenKind::RightParen {
+        let mut modifiers = Vec::new();
 
-        if p.tokens[p.i].kind == TokenKind::Const {
-            parameter_modifiers.push(Modifiers::Constant);
+        // Modifiers
+        if p.token.kind == TokenKind::Mut {
+            modifiers.push(Modifiers::Mutable);
             p.advance();
         }
 
-        // Variable identifier (TODO: add support for literals)
-        if p.tokens[p.i].kind == TokenKind::RightParen {
+        if p.token.kind == TokenKind::Const {
+            modifiers.push(Modifiers::Constant);
             p.advance();
-
-            break;
-        } else {
-            match p.tokens[p.i].kind.clone() {
-                TokenKind::Unknown(a) => parameter_name = a,
-                _ => panic!("syntax error??"),
-            };
         }
 
-        p.advance();
-
-        if p.tokens[p.i].kind == TokenKind::Comma {
-            parameters.push((parameter_name, parameter_modifiers));
-
-            p.advance();
-        } else if p.tokens[p.i].kind == TokenKind::RightParen {
-            parameters.push((parameter_name, parameter_modifiers));
+        let value = parse_expression(p)?;
 
-            p.advance();
+        args.push(Arg { modifiers, value });
 
-            break;
+        match p.token.kind {
+            TokenKind::Comma => p.advance(),
+            TokenKind::RightParen => {}
+            _ => {
+                return Err(p
+                    .unexpected("`,` or `)`")
+                    .with_secondary(open, "the arguments start here"))
+            }
         }
     }
 
-    p.add_token(ParsedToken::FunctionCall(name, parameters));
+    p.advance();
+
+    Ok(Expr {
+        kind: ExprKind::Call(Call { name, args }),
+        span: start.to(p.prev_span()),
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    // use crate::{
-    //     lexer::*,
-    //     parser::{functions::parse_function, functions::parse_function_call, Parser},
-    // };
-    //
-    // #[test]
-    // fn test_parse_function() {
-    //     let code = r#"fn main() {}"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function(&mut parser);
-    //
-    //     let c = format!("{:?}", parser.output);
-    //     let d = "[Function(\"main\", [], Void)]".to_string();
-    //
-    //     assert_eq!(c, d);
-    // }
-    // #[test]
-    // fn test_parse_function_with_parameters() {
-    //     let code = r#"fn main(foo void) {}"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[Function(\"main\", [(\"foo\", [], Void)], Void)]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
-    // #[test]
-    // fn test_parse_function_with_return_type() {
-    //     let code = r#"fn main() i32 {}"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[Function(\"main\", [], I32)]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
-    // #[test]
-    // fn test_parse_function_with_multiple_parameters() {
-    //     let code = r#"fn main(foo i32, bar void) {}"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[Function(\"main\", [(\"foo\", [], I32), (\"bar\", [], Void)], Void)]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
-    // #[test]
-    // fn test_parse_function_with_multiple_parameters_and_a_return_type() {
-    //     let code = r#"fn main(foo i32, bar void) u32 {}"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[Function(\"main\", [(\"foo\", [], I32), (\"bar\", [], Void)], U32)]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
-    // #[test]
-    // fn test_parse_function_call() {
-    //     let code = r#"foo();"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function_call(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[FunctionCall(\"foo\", [])]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
-    // #[test]
-    // fn test_parse_function_call_with_parameters() {
-    //     let code = r#"foo(bar);"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function_call(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[FunctionCall(\"foo\", [(\"bar\", [])])]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
-    // #[test]
-    // fn test_parse_function_call_with_multiple_parameters() {
-    //     let code = r#"foo(bar, idk);"#;
-    //
-    //     let tokens = lexer(code);
-    //
-    //     let mut parser = Parser::new(tokens);
-    //
-    //     parse_function_call(&mut parser);
-    //
-    //     let a = format!("{:?}", parser.output);
-    //     let b = "[FunctionCall(\"foo\", [(\"bar\", []), (\"idk\", [])])]".to_string();
-    //
-    //     assert_eq!(a, b);
-    // }
+    use super::*;
+    use crate::parser::ast::Stmt;
+    use crate::span::FileId;
+
+    fn parser(src: &str) -> Parser<'_> {
+        Parser::new(Lexer::new(src, FileId(0)))
+    }
+
+    #[test]
+    fn test_parse_function() {
+        let f = parse_function(&mut parser("fn main() {}")).unwrap();
+
+        assert_eq!(f.name, "main");
+        assert!(f.params.is_empty());
+        assert_eq!(f.return_type, Type::Void);
+        assert_eq!(f.body.map(|b| b.stmts.len()), Some(0));
+    }
+    #[test]
+    fn test_parse_function_with_multiple_parameters_and_a_return_type() {
+        let f = parse_function(&mut parser("fn main(foo i32, bar void) u32 {}")).unwrap();
+
+        let params = f
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), &p.ty))
+            .collect::<Vec<_>>();
+
+        assert_eq!(params, vec![("foo", &Type::I32), ("bar", &Type::Void)]);
+        assert_eq!(f.return_type, Type::U32);
+    }
+    #[test]
+    fn test_parse_function_with_modifiers() {
+        let f = parse_function(&mut parser("fn main(a mut i32, b const) {}")).unwrap();
+
+        let params = f
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.modifiers.clone(), &p.ty))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            params,
+            vec![
+                ("a", vec![Modifiers::Mutable], &Type::I32),
+                ("b", vec![Modifiers::Constant], &Type::None)
+            ]
+        );
+    }
+    #[test]
+    fn test_parse_function_parameter_errors() {
+        let messages = ["fn f(a i32 b i32) {}", "fn f(a mut b) {}", "fn f(a, 1) {}"]
+            .iter()
+            .map(|code| parse_function(&mut parser(code)).unwrap_err().message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "expected `,` or `)`, found identifier `b`",
+                "expected `,` or `)`, found identifier `b`",
+                "expected a parameter name or `)`, found integer `1`",
+            ]
+        );
+    }
+    #[test]
+    fn test_parse_function_call_with_modifiers() {
+        let call = parse_function_call(&mut parser("foo(mut a, const b);")).unwrap();
+
+        let ExprKind::Call(call) = call.kind else {
+            panic!("not a call: {:?}", call);
+        };
+
+        assert_eq!(
+            call.args
+                .iter()
+                .map(|a| a.modifiers.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![Modifiers::Mutable], vec![Modifiers::Constant]]
+        );
+    }
+    #[test]
+    fn test_parse_function_with_body() {
+        let f = parse_function(&mut parser("fn main() { foo(a); { return; } }")).unwrap();
+        let body = f.body.unwrap();
+
+        assert!(matches!(
+            body.stmts.as_slice(),
+            [Stmt::Expr(_), Stmt::Block(_)]
+        ));
+        assert_eq!(f.span, Span::new(FileId(0), 0, 33, 1, 1));
+    }
+    #[test]
+    fn test_parse_function_declaration() {
+        let mut p = parser("fn foo() i32; fn bar() {}");
+        let f = parse_function(&mut p).unwrap();
+
+        assert_eq!(f.return_type, Type::I32);
+        assert_eq!(f.body, None);
+        assert_eq!(f.span, Span::new(FileId(0), 0, 13, 1, 1));
+        assert_eq!(p.token.kind, TokenKind::Fn);
+    }
+    #[test]
+    fn test_parse_function_call_with_expressions() {
+        let call = parse_function_call(&mut parser("foo(a + 1, bar(2) * 3, 'c', 1.5);")).unwrap();
+
+        let ExprKind::Call(call) = call.kind else {
+            panic!("not a call: {:?}", call);
+        };
+
+        assert!(matches!(
+            call.args
+                .iter()
+                .map(|a| &a.value.kind)
+                .collect::<Vec<_>>()
+                .as_slice(),
+            [
+                ExprKind::Binary(..),
+                ExprKind::Binary(..),
+                ExprKind::Char('c'),
+                ExprKind::Float(_)
+            ]
+        ));
+    }
+    #[test]
+    fn test_parse_function_call_errors() {
+        let messages = ["foo(a b)", "foo(a,", "foo(;"]
+            .iter()
+            .map(|code| parse_function_call(&mut parser(code)).unwrap_err().message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "expected `,` or `)`, found identifier `b`",
+                "expected an expression, found end of file",
+                "expected an expression, found `;`",
+            ]
+        );
+    }
+    #[test]
+    fn test_parse_function_call() {
+        let call = parse_function_call(&mut parser("foo(bar, \"idk\", 1);")).unwrap();
+
+        let ExprKind::Call(call) = call.kind else {
+            panic!("not a call: {:?}", call);
+        };
+
+        assert_eq!(call.name, "foo");
+        assert_eq!(
+            call.args
+                .iter()
+                .map(|a| a.value.kind.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                ExprKind::Ident("bar".to_string()),
+                ExprKind::Str("idk".to_string()),
+                ExprKind::Int(1, Type::None)
+            ]
+        );
+    }
 }
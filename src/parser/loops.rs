@@ -0,0 +1,192 @@
+use super::ast::{For, Stmt, While};
+use super::conditionals::parse_condition;
+use super::expressions::parse_expression;
+use super::statements::{expect_semicolon, parse_block};
+use super::*;
+
+/// Parses the body of a loop, `break` and `continue` are allowed in it.
+fn parse_loop_body(p: &mut Parser) -> PResult<ast::Block> {
+    p.loop_depth += 1;
+    let body = parse_block(p);
+    p.loop_depth -= 1;
+
+    body
+}
+
+/// Parses a while loop.
+pub fn parse_while(p: &mut Parser) -> PResult<While> {
+    // while (expr) {}
+    let start = p.token.span;
+
+    if p.token.kind != TokenKind::While {
+        return Err(p.unexpected("`while`"));
+    }
+
+    p.advance();
+
+    let cond = parse_condition(p)?;
+    let body = parse_loop_body(p)?;
+
+    Ok(While {
+        cond,
+        span: start.to(body.span),
+        body,
+    })
+}
+
+/// Parses a for loop over a range.
+pub fn parse_for(p: &mut Parser) -> PResult<For> {
+    // for i in a..b {} or for i in a..=b {}
+    let start = p.token.span;
+
+    if p.token.kind != TokenKind::For {
+        return Err(p.unexpected("`for`"));
+    }
+
+    p.advance();
+
+    let var = match p.token.kind.clone() {
+        TokenKind::Unknown(a) => {
+            p.advance();
+            a.to_string()
+        }
+        _ => return Err(p.unexpected("a variable name")),
+    };
+
+    if p.token.kind != TokenKind::In {
+        return Err(p.unexpected("`in`"));
+    }
+
+    p.advance();
+
+    let range_start = parse_expression(p)?;
+
+    let inclusive = match p.token.kind {
+        TokenKind::DoubleDot => false,
+        TokenKind::DoubleDotEq => true,
+        _ => return Err(p.unexpected("`..` or `..=`")),
+    };
+
+    p.advance();
+
+    let end = parse_expression(p)?;
+    let body = parse_loop_body(p)?;
+
+    Ok(For {
+        var,
+        start: range_start,
+        end,
+        inclusive,
+        span: start.to(body.span),
+        body,
+    })
+}
+
+/// Parses `break;` or `continue;`. Using them outside of a loop is reported, but they are
+/// still parsed.
+pub fn parse_break_or_continue(p: &mut Parser) -> PResult<Stmt> {
+    let token = p.token.clone();
+
+    let stmt = match token.kind {
+        TokenKind::Break => Stmt::Break(token.span),
+        TokenKind::Continue => Stmt::Continue(token.span),
+        _ => return Err(p.unexpected("`break` or `continue`")),
+    };
+
+    if p.loop_depth == 0 {
+        p.diagnostics.push(
+            Diagnostic::error(format!("{} outside of a loop", token.kind))
+                .with_code(ErrorCode::OutsideOfLoop)
+                .with_label(
+                    token.span,
+                    format!("can't {} outside of a loop", token.kind),
+                ),
+        );
+    }
+
+    p.advance();
+    expect_semicolon(p)?;
+
+    Ok(stmt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::ExprKind;
+    use crate::span::FileId;
+
+    fn parser(src: &str) -> Parser<'_> {
+        Parser::new(Lexer::new(src, FileId(0)))
+    }
+
+    #[test]
+    fn test_parse_while() {
+        let mut p = parser("while (a < 10) { a; break; }");
+        let w = parse_while(&mut p).unwrap();
+
+        assert!(matches!(w.cond.kind, ExprKind::Binary(..)));
+        assert!(matches!(
+            w.body.stmts.as_slice(),
+            [Stmt::Expr(_), Stmt::Break(_)]
+        ));
+        assert_eq!(w.span, Span::new(FileId(0), 0, 28, 1, 1));
+        assert!(p.diagnostics.is_empty());
+    }
+    #[test]
+    fn test_parse_for() {
+        let mut p = parser("for i in 0..n + 1 { continue; }");
+        let f = parse_for(&mut p).unwrap();
+
+        assert_eq!(f.var, "i");
+        assert_eq!(f.start.kind, ExprKind::Int(0, Type::None));
+        assert!(matches!(f.end.kind, ExprKind::Binary(..)));
+        assert!(!f.inclusive);
+        assert!(matches!(f.body.stmts.as_slice(), [Stmt::Continue(_)]));
+        assert!(p.diagnostics.is_empty());
+    }
+    #[test]
+    fn test_parse_for_inclusive() {
+        let f = parse_for(&mut parser("for i in a..=b {}")).unwrap();
+
+        assert_eq!(f.start.kind, ExprKind::Ident("a".to_string()));
+        assert_eq!(f.end.kind, ExprKind::Ident("b".to_string()));
+        assert!(f.inclusive);
+    }
+    #[test]
+    fn test_parse_for_errors() {
+        let messages = ["for 1 in a..b {}", "for i a..b {}", "for i in a {}"]
+            .iter()
+            .map(|code| parse_for(&mut parser(code)).unwrap_err().message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "expected a variable name, found integer `1`",
+                "expected `in`, found identifier `a`",
+                "expected `..` or `..=`, found `{`",
+            ]
+        );
+    }
+    #[test]
+    fn test_break_outside_of_loop() {
+        let mut p = parser("{ break; while (a) { { continue; } } continue; }");
+        let block = statements::parse_block(&mut p).unwrap();
+
+        let errors = p
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.primary_span().map(|s| s.col)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(block.stmts.len(), 3);
+        assert_eq!(
+            errors,
+            vec![
+                (Some(ErrorCode::OutsideOfLoop), Some(3)),
+                (Some(ErrorCode::OutsideOfLoop), Some(38))
+            ]
+        );
+    }
+}
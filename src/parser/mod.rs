@@ -1,14 +1,18 @@
+pub mod ast;
 mod conditionals;
 mod expressions;
 mod functions;
 mod loops;
+mod statements;
+mod variables;
 
-use super::lexer::{Lexer, Token, TokenKind};
-use conditionals::*;
-use expressions::*;
-use functions::*;
+use std::fmt;
 
-#[derive(Debug, Clone)]
+use super::lexer::{DocStyle, Lexer, Token, TokenKind};
+use crate::diagnostics::{Diagnostic, Diagnostics, ErrorCode};
+use crate::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Void,
     I32,
@@ -19,72 +23,579 @@ pub enum Type {
     None,
 }
 
-#[derive(Debug)]
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Void => write!(f, "void"),
+            Type::I32 => write!(f, "i32"),
+            Type::U32 => write!(f, "u32"),
+            Type::U8 => write!(f, "u8"),
+            Type::String => write!(f, "string"),
+            Type::None => write!(f, "_"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Modifiers {
     Mutable,
     Constant,
 }
 
+/// Pulls tokens from the lexer as it goes, so only the tokens that are being looked at are kept
+/// in memory.
 #[derive(Debug)]
-pub struct Parser {
-    tokens: Vec<Token>,
-    i: usize,
-    len: usize,
-    output: Vec<ParsedToken>,
+pub struct Parser<'src> {
+    lexer: Lexer<'src>,
+    /// The token at the "cursor".
+    token: Token<'src>,
+    /// Span of the token before the "cursor".
+    prev: Span,
+    /// How many loops the "cursor" is in, `break` and `continue` are only allowed in loops.
+    loop_depth: usize,
+    /// Doc comments before the "cursor", for the item at the "cursor" to take.
+    docs: Vec<Token<'src>>,
+    /// Errors the parser recovered from, and the ones from the lexer.
+    diagnostics: Diagnostics,
 }
 
-impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
+impl<'src> Parser<'src> {
+    fn new(mut lexer: Lexer<'src>) -> Self {
+        let mut docs = Vec::new();
+        let mut diagnostics = Diagnostics::new();
+        let token = Parser::next_token(&mut lexer, &mut docs, &mut diagnostics);
+
         Self {
-            len: tokens.len(),
-            tokens,
-            i: 0,
-            output: Vec::new(),
+            lexer,
+            prev: token.span,
+            token,
+            loop_depth: 0,
+            docs,
+            diagnostics,
         }
     }
-    /// Advances the "cursor" by one.
+    /// Takes the next token from the lexer. Its errors are reported on the way, and doc
+    /// comments are put aside in `docs` for the item after them.
+    fn next_token(
+        lexer: &mut Lexer<'src>,
+        docs: &mut Vec<Token<'src>>,
+        diagnostics: &mut Diagnostics,
+    ) -> Token<'src> {
+        loop {
+            match lexer.next() {
+                Some(Ok(token)) if matches!(token.kind, TokenKind::DocComment(..)) => {
+                    docs.push(token)
+                }
+                Some(Ok(token)) => return token,
+                Some(Err(e)) => diagnostics.push(e),
+                // The lexer always ends with Eof, and the parser doesn't go past it.
+                None => unreachable!("the parser went past the end of the file"),
+            }
+        }
+    }
+    /// Advances the "cursor" by one. Stays on the last token (Eof) so the parser can't run
+    /// past the end.
     fn advance(&mut self) {
-        self.i += 1;
-    }
-    /// Advances the "cursor" by `val`.
-    /// Use advance() when only advancing by one.
-    ///
-    /// # Panics
-    ///
-    /// Panics on debug mode if `val` is 0 and recommends you to use advance.
-    fn advance_with(&mut self, val: usize) {
-        debug_assert_ne!(
-            val, 0,
-            "Instead of using advance_with(), you should use advance() instead, when advancing only by one."
+        if self.token.kind == TokenKind::Eof {
+            return;
+        }
+
+        // The token at the "cursor" didn't take them.
+        self.report_unused_docs();
+
+        let token = Parser::next_token(&mut self.lexer, &mut self.docs, &mut self.diagnostics);
+
+        self.prev = std::mem::replace(&mut self.token, token).span;
+    }
+    /// Takes the doc comments before the "cursor" and joins them into one string, one line
+    /// per comment. The space after `///` is left out.
+    fn take_docs(&mut self) -> Option<String> {
+        let docs = std::mem::take(&mut self.docs);
+        let mut lines = Vec::new();
+
+        for doc in &docs {
+            match doc.kind {
+                TokenKind::DocComment(DocStyle::Outer, text) => {
+                    lines.push(text.strip_prefix(' ').unwrap_or(text))
+                }
+                _ => self.misplaced_inner_doc(doc.span),
+            }
+        }
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+    /// Takes the `//!` comments at the start of the file, they document the whole file.
+    fn take_module_docs(&mut self) -> Option<String> {
+        let count = self
+            .docs
+            .iter()
+            .take_while(|doc| matches!(doc.kind, TokenKind::DocComment(DocStyle::Inner, _)))
+            .count();
+
+        if count == 0 {
+            return None;
+        }
+
+        let lines = self
+            .docs
+            .drain(..count)
+            .map(|doc| match doc.kind {
+                TokenKind::DocComment(_, text) => text.strip_prefix(' ').unwrap_or(text),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        Some(lines.join("\n"))
+    }
+    /// Reports the doc comments that no item took, they would be lost otherwise.
+    fn report_unused_docs(&mut self) {
+        let docs = std::mem::take(&mut self.docs);
+        let outer = docs
+            .iter()
+            .filter(|doc| matches!(doc.kind, TokenKind::DocComment(DocStyle::Outer, _)))
+            .map(|doc| doc.span)
+            .collect::<Vec<_>>();
+
+        if let (Some(first), Some(last)) = (outer.first(), outer.last()) {
+            self.diagnostics.push(
+                Diagnostic::warning("unused doc comment")
+                    .with_code(ErrorCode::UnusedDocComment)
+                    .with_label(first.to(*last), "doesn't document anything")
+                    .with_note("only functions and variables can be documented"),
+            );
+        }
+
+        for doc in &docs {
+            if let TokenKind::DocComment(DocStyle::Inner, _) = doc.kind {
+                self.misplaced_inner_doc(doc.span);
+            }
+        }
+    }
+    fn misplaced_inner_doc(&mut self, span: Span) {
+        self.diagnostics.push(
+            Diagnostic::error("inner doc comments must be at the start of the file")
+                .with_code(ErrorCode::MisplacedInnerDoc)
+                .with_label(span, "not at the start of the file")
+                .with_note("use `///` to document the item after it"),
         );
+    }
+    /// The kind of the token after the "cursor", without advancing. Errors from the lexer
+    /// and doc comments are skipped here, advance() takes care of them.
+    fn peek(&mut self) -> Option<&TokenKind<'src>> {
+        let mut n = 0;
+
+        while let Some(Err(_))
+        | Some(Ok(Token {
+            kind: TokenKind::DocComment(..),
+            ..
+        })) = self.lexer.peek_nth(n)
+        {
+            n += 1;
+        }
+
+        match self.lexer.peek_nth(n) {
+            Some(Ok(token)) => Some(&token.kind),
+            _ => None,
+        }
+    }
+    /// Span of the token before the "cursor", where the thing that was just parsed ends.
+    fn prev_span(&self) -> Span {
+        self.prev
+    }
+    /// Creates an "expected ..., found ..." error pointing at the current token.
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        let token = &self.token;
 
-        self.i += val;
+        Diagnostic::error(format!("expected {}, found {}", expected, token.kind))
+            .with_code(ErrorCode::UnexpectedToken)
+            .with_label(token.span, format!("expected {}", expected))
     }
-    /// Adds `token` to the `tokens` list on the object.
-    fn add_token(&mut self, token: ParsedToken) {
-        self.output.push(token)
+    /// Skips tokens after an error until parsing can continue: after a `;` or `}`, or before
+    /// the next `fn`. Anything inside `{ }` is skipped as a whole. A `}` that closes a block
+    /// that was opened before the error isn't skipped, the block still needs it.
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+
+        loop {
+            match self.token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Fn | TokenKind::RightBracket if depth == 0 => return,
+                TokenKind::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenKind::LeftBracket => depth += 1,
+                TokenKind::RightBracket => {
+                    self.advance();
+
+                    if depth <= 1 {
+                        return;
+                    }
+
+                    depth -= 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+    /// Reports `error`, skips to a place where parsing can continue and returns the span of
+    /// what was skipped, for the error node. `start` is the span of the token where the failed
+    /// item began.
+    fn recover(&mut self, start: Span, error: Diagnostic) -> Span {
+        self.diagnostics.push(error);
+
+        // Always skip at least one token, otherwise we could fail on the same token forever.
+        if self.token.span == start {
+            self.advance();
+        }
+
+        self.synchronize();
+
+        if self.token.span.offset > start.offset {
+            start.to(self.prev)
+        } else {
+            start
+        }
     }
 }
 
-#[derive(Debug)]
-pub enum ParsedToken {
-    /// Represents a function call. (function to be called, parameters)
-    FunctionCall(String, CallParameters),
-    /// Represents a function definition. (name, parameters, return type)
-    Function(String, Parameters, Type),
-    /// An expression.
-    Expression(Expression),
-    /// An if statement. (expression)
-    If(Expression),
-    /// End of file.
-    Eof,
+/// Parsing functions return the error instead of reporting it, the caller decides what to
+/// do with it.
+pub type PResult<T> = Result<T, Diagnostic>;
+
+/// Parses one top level item.
+fn parse_item(p: &mut Parser) -> PResult<ast::Item> {
+    match p.token.kind {
+        TokenKind::Fn => functions::parse_function(p).map(ast::Item::Fn),
+        TokenKind::Var | TokenKind::Const => variables::parse_variable(p).map(ast::Item::Global),
+        _ => Err(p
+            .unexpected("an item")
+            .with_note("only functions and global variables can be at the top level of a file")),
+    }
 }
 
-pub fn parser(tokens: Vec<Token>) -> Vec<ParsedToken> {
-    let mut parser = Parser::new(tokens);
+/// Parses everything until the end of the file. Errors are added to `diagnostics` and the
+/// parser recovers from them, so all syntax errors in a file are reported in one go.
+pub fn parser(lexer: Lexer, diagnostics: &mut Diagnostics) -> ast::Module {
+    let mut parser = Parser::new(lexer);
+    let mut items = Vec::new();
+    let doc = parser.take_module_docs();
+
+    while parser.token.kind != TokenKind::Eof {
+        let start = parser.token.span;
+        let is_fn = parser.token.kind == TokenKind::Fn;
 
-    functions::parse_function(&mut parser);
-    functions::parse_function_call(&mut parser);
+        match parse_item(&mut parser) {
+            Ok(item) => items.push(item),
+            // The signature of a function is broken, but its body can still be checked.
+            Err(e) if is_fn && parser.token.kind == TokenKind::LeftBracket => {
+                parser.diagnostics.push(e);
+
+                let span = match statements::parse_block(&mut parser) {
+                    Ok(_) => start.to(parser.prev),
+                    Err(e) => parser.recover(start, e),
+                };
+                items.push(ast::Item::Error(span));
+            }
+            Err(e) => {
+                let span = parser.recover(start, e);
+                items.push(ast::Item::Error(span));
+            }
+        }
+    }
 
-    vec![]
+    // Doc comments at the end of the file.
+    parser.report_unused_docs();
+    diagnostics.append(&mut parser.diagnostics);
+
+    ast::Module { doc, items }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::FileId;
+
+    fn parse(src: &str) -> (ast::Module, Diagnostics) {
+        let mut diagnostics = Diagnostics::new();
+        let module = parser(Lexer::new(src, FileId(0)), &mut diagnostics);
+
+        (module, diagnostics)
+    }
+
+    #[test]
+    fn test_parser_reports_every_error() {
+        let (module, diagnostics) = parse("fn (a)\nfn main(1) {}\nfn ok(b i32) u32 { a; }");
+
+        let spans = diagnostics
+            .iter()
+            .map(|d| d.primary_span().map(|s| (s.line, s.col)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(spans, vec![Some((1, 4)), Some((2, 9))]);
+        assert_eq!(
+            diagnostics.iter().next().unwrap().message,
+            "expected a function name, found `(`"
+        );
+        assert!(matches!(
+            module.items.as_slice(),
+            [
+                ast::Item::Error(_),
+                ast::Item::Error(_),
+                ast::Item::Fn(ast::FnDecl {
+                    return_type: Type::U32,
+                    ..
+                }),
+            ]
+        ));
+    }
+    #[test]
+    fn test_parser_reports_errors_in_every_broken_function() {
+        let src = "fn a() {\n    foo();\n\nfn b() {\n    1 +;\n}\n\nfn c() {\n    var = 2;\n}\n\n\
+                   fn d( {\n    x +;\n}\nfn e() {}";
+        let (module, diagnostics) = parse(src);
+
+        let errors = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.primary_span().unwrap().line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            vec![
+                ("expected `}`, found `fn`", 4),
+                ("an operator is missing an operand", 5),
+                ("expected a variable name, found `=`", 9),
+                ("expected a parameter name or `)`, found `{`", 12),
+                ("an operator is missing an operand", 13),
+            ]
+        );
+        assert_eq!(
+            diagnostics.iter().next().unwrap().labels[1].message,
+            "this `{` is never closed"
+        );
+        assert!(matches!(
+            module.items.as_slice(),
+            [
+                ast::Item::Error(_),
+                ast::Item::Fn(_),
+                ast::Item::Fn(_),
+                ast::Item::Error(_),
+                ast::Item::Fn(_)
+            ]
+        ));
+    }
+    #[test]
+    fn test_parser_synchronizes_on_semicolon() {
+        let (module, diagnostics) = parse("foo(1 2);\nfn main() {}");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            module.items.as_slice(),
+            [ast::Item::Error(span), ast::Item::Fn(_)] if span.len == 9
+        ));
+    }
+    #[test]
+    fn test_parser_skips_blocks_when_synchronizing() {
+        let (module, diagnostics) = parse("x { a; b; } fn main() {}");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            module.items.as_slice(),
+            [ast::Item::Error(_), ast::Item::Fn(_)]
+        ));
+    }
+    #[test]
+    fn test_parser_rejects_other_things_at_the_top_level() {
+        let (module, diagnostics) = parse("fn a() {}\n1 + 2;\nfn b() {}\nreturn;");
+        let messages = diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "expected an item, found integer `1`",
+                "expected an item, found `return`"
+            ]
+        );
+        assert!(matches!(
+            module.items.as_slice(),
+            [
+                ast::Item::Fn(_),
+                ast::Item::Error(_),
+                ast::Item::Fn(_),
+                ast::Item::Error(_)
+            ]
+        ));
+    }
+    #[test]
+    fn test_parser_globals() {
+        let (module, diagnostics) =
+            parse("var a: i32 = 1;\nvar b = a;\nconst c = b;\nvar mut d = 2;\nfn main() {}");
+
+        let modifiers = module
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                ast::Item::Global(var) => Some(var.modifiers.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert!(diagnostics.is_empty());
+        assert!(matches!(
+            module.items.as_slice(),
+            [
+                ast::Item::Global(ast::Let { ty: Type::I32, .. }),
+                ast::Item::Global(ast::Let { ty: Type::None, .. }),
+                ast::Item::Global(_),
+                ast::Item::Global(_),
+                ast::Item::Fn(_)
+            ]
+        ));
+        assert_eq!(
+            modifiers,
+            vec![
+                vec![],
+                vec![],
+                vec![Modifiers::Constant],
+                vec![Modifiers::Mutable]
+            ]
+        );
+    }
+    #[test]
+    fn test_parser_reports_lexer_errors() {
+        let (module, diagnostics) = parse("fn main() { a @ b; }\nfn f() { 'x }");
+
+        let codes = diagnostics.iter().map(|d| d.code).collect::<Vec<_>>();
+
+        // In the order they are in the file.
+        assert_eq!(
+            codes,
+            vec![
+                Some(ErrorCode::UnknownCharacter),
+                Some(ErrorCode::UnexpectedToken),
+                Some(ErrorCode::UnterminatedChar),
+                Some(ErrorCode::UnexpectedToken),
+            ]
+        );
+        assert_eq!(module.items.len(), 2);
+    }
+    #[test]
+    fn test_parser_hello_world() {
+        let (module, diagnostics) = parse("fn main() {\n    println(\"Hello, World!\");\n}\n");
+
+        assert!(diagnostics.is_empty());
+
+        let [ast::Item::Fn(main)] = module.items.as_slice() else {
+            panic!("expected one function: {:?}", module.items);
+        };
+        let stmts = &main.body.as_ref().unwrap().stmts;
+
+        assert!(matches!(
+            stmts.as_slice(),
+            [ast::Stmt::Expr(ast::Expr { kind: ast::ExprKind::Call(call), .. })]
+                if call.name == "println"
+                    && call.args[0].value.kind == ast::ExprKind::Str("Hello, World!".to_string())
+        ));
+    }
+    #[test]
+    fn test_parser_attaches_doc_comments() {
+        let (module, diagnostics) = parse(
+            "//! A module.\n//!\n//! More about it.\n\n/// Adds.\n///   Indented.\nfn add() {\n    /// A local.\n    var a = 1;\n}\n/// A global.\nvar b = 2;\nfn none() {}",
+        );
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(module.doc.as_deref(), Some("A module.\n\nMore about it."));
+
+        let [ast::Item::Fn(add), ast::Item::Global(b), ast::Item::Fn(none)] =
+            module.items.as_slice()
+        else {
+            panic!("unexpected items: {:?}", module.items);
+        };
+        let [ast::Stmt::Let(a)] = add.body.as_ref().unwrap().stmts.as_slice() else {
+            panic!("unexpected statements: {:?}", add.body);
+        };
+
+        assert_eq!(add.doc.as_deref(), Some("Adds.\n  Indented."));
+        assert_eq!(a.doc.as_deref(), Some("A local."));
+        assert_eq!(b.doc.as_deref(), Some("A global."));
+        assert_eq!(none.doc, None);
+    }
+    #[test]
+    fn test_parser_reports_unused_doc_comments() {
+        let cases = [
+            (
+                "fn main() { /// Nothing.\n    foo(); }",
+                vec![ErrorCode::UnusedDocComment],
+            ),
+            (
+                "fn main() {}\n/// At the end.\n/// Two lines.",
+                vec![ErrorCode::UnusedDocComment],
+            ),
+            (
+                "fn main() {}\n//! Too late.\nfn f() {}",
+                vec![ErrorCode::MisplacedInnerDoc],
+            ),
+            (
+                "fn main() { //! In here.\n}",
+                vec![ErrorCode::MisplacedInnerDoc],
+            ),
+            ("//! Fine.\n/// Also fine.\nfn main() {}", vec![]),
+        ];
+
+        for (i, (src, expected)) in cases.iter().enumerate() {
+            let (_, diagnostics) = parse(src);
+            let codes = diagnostics
+                .iter()
+                .filter_map(|d| d.code)
+                .collect::<Vec<_>>();
+
+            assert_eq!(&codes, expected, "Test case {} failed", i + 1);
+        }
+
+        let (_, diagnostics) = parse("fn main() {}\n/// At the end.\n/// Two lines.");
+        let warning = diagnostics.iter().next().unwrap();
+
+        assert_eq!(warning.severity, crate::diagnostics::Severity::Warning);
+        assert_eq!(
+            warning.primary_span(),
+            Some(Span::new(FileId(0), 13, 30, 2, 1))
+        );
+    }
+    #[test]
+    fn test_parser_builds_a_module() {
+        let (module, diagnostics) = parse("fn main() {}\nfn add(a i32, b i32) i32 {}");
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(module.items.len(), 2);
+
+        let ast::Item::Fn(add) = &module.items[1] else {
+            panic!("not a function: {:?}", module.items[1]);
+        };
+
+        assert_eq!(add.name, "add");
+        assert_eq!(add.return_type, Type::I32);
+        assert_eq!(
+            add.params
+                .iter()
+                .map(|p| (p.name.as_str(), &p.ty))
+                .collect::<Vec<_>>(),
+            vec![("a", &Type::I32), ("b", &Type::I32)]
+        );
+        assert_eq!(add.span, Span::new(FileId(0), 13, 27, 2, 1));
+        assert_eq!(
+            add.body.as_ref().map(|b| b.span),
+            Some(Span::new(FileId(0), 38, 2, 2, 26))
+        );
+    }
 }
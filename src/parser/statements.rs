@@ -0,0 +1,193 @@
+use super::ast::{Block, Stmt};
+use super::conditionals::parse_if_statement;
+use super::expressions::parse_expression;
+use super::loops::{parse_break_or_continue, parse_for, parse_while};
+use super::variables::parse_variable;
+use super::*;
+
+/// Parses a `{ ... }` block. Errors in the statements are reported and the parser continues
+/// with the next statement, so only a missing `}` makes the whole block fail. A `fn` can't be
+/// in a block, so the `}` is missing if one comes first.
+pub fn parse_block(p: &mut Parser) -> PResult<Block> {
+    let start = p.token.span;
+
+    if p.token.kind != TokenKind::LeftBracket {
+        return Err(p.unexpected("`{`"));
+    }
+
+    p.advance();
+
+    let mut stmts = Vec::new();
+
+    loop {
+        match p.token.kind {
+            TokenKind::RightBracket => {
+                p.advance();
+                break;
+            }
+            TokenKind::Eof | TokenKind::Fn => {
+                return Err(p
+                    .unexpected("`}`")
+                    .with_secondary(start, "this `{` is never closed"))
+            }
+            _ => {}
+        }
+
+        let before = p.token.span;
+
+        match parse_statement(p) {
+            Ok(stmt) => stmts.push(stmt),
+            Err(e) => {
+                let span = p.recover(before, e);
+                stmts.push(Stmt::Error(span));
+            }
+        }
+    }
+
+    Ok(Block {
+        stmts,
+        span: start.to(p.prev_span()),
+    })
+}
+
+/// Parses one statement.
+pub fn parse_statement(p: &mut Parser) -> PResult<Stmt> {
+    match p.token.kind {
+        TokenKind::LeftBracket => parse_block(p).map(Stmt::Block),
+        TokenKind::Return => parse_return(p),
+        TokenKind::If => parse_if_statement(p).map(Stmt::If),
+        TokenKind::Elif | TokenKind::Else => Err(p.unexpected("a statement")),
+        TokenKind::Var | TokenKind::Const => parse_variable(p).map(Stmt::Let),
+        TokenKind::While => parse_while(p).map(Stmt::While),
+        TokenKind::For => parse_for(p).map(Stmt::For),
+        TokenKind::Break | TokenKind::Continue => parse_break_or_continue(p),
+        _ => parse_expression_statement(p),
+    }
+}
+
+/// `return;` or `return expr;`
+fn parse_return(p: &mut Parser) -> PResult<Stmt> {
+    let start = p.token.span;
+
+    p.advance();
+
+    let value = if p.token.kind == TokenKind::Semicolon {
+        None
+    } else {
+        Some(parse_expression(p)?)
+    };
+
+    expect_semicolon(p)?;
+
+    Ok(Stmt::Return(value, start.to(p.prev_span())))
+}
+
+/// An expression followed by a `;`.
+fn parse_expression_statement(p: &mut Parser) -> PResult<Stmt> {
+    let expr = parse_expression(p)?;
+
+    expect_semicolon(p)?;
+
+    Ok(Stmt::Expr(expr))
+}
+
+pub fn expect_semicolon(p: &mut Parser) -> PResult<()> {
+    if p.token.kind != TokenKind::Semicolon {
+        return Err(p.unexpected("`;`"));
+    }
+
+    p.advance();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::ExprKind;
+    use crate::parser::expressions::OpKind;
+    use crate::span::FileId;
+
+    fn parse(src: &str) -> (PResult<Block>, Diagnostics) {
+        let mut diagnostics = Diagnostics::new();
+        let mut p = Parser::new(Lexer::new(src, FileId(0)));
+        let block = parse_block(&mut p);
+
+        diagnostics.append(&mut p.diagnostics);
+
+        (block, diagnostics)
+    }
+
+    #[test]
+    fn test_parse_block() {
+        let (block, diagnostics) = parse("{ foo(a); a + 2; { return; } var b = a; return b; }");
+        let block = block.unwrap();
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(block.span.len, 51);
+        assert!(matches!(
+            block.stmts.as_slice(),
+            [
+                Stmt::Expr(ast::Expr {
+                    kind: ExprKind::Call(_),
+                    ..
+                }),
+                Stmt::Expr(ast::Expr {
+                    kind: ExprKind::Binary(..),
+                    ..
+                }),
+                Stmt::Block(Block { stmts: inner, .. }),
+                Stmt::Let(_),
+                Stmt::Return(Some(_), _),
+            ] if matches!(inner.as_slice(), [Stmt::Return(None, _)])
+        ));
+    }
+    #[test]
+    fn test_parse_block_with_assignments() {
+        let (block, diagnostics) = parse("{ i = i + 1; i += 2; a = b = 0; }");
+        let block = block.unwrap();
+
+        assert!(diagnostics.is_empty());
+        assert!(matches!(
+            block.stmts.as_slice(),
+            [
+                Stmt::Expr(ast::Expr {
+                    kind: ExprKind::Assign(None, ..),
+                    ..
+                }),
+                Stmt::Expr(ast::Expr {
+                    kind: ExprKind::Assign(Some(OpKind::Add), ..),
+                    ..
+                }),
+                Stmt::Expr(ast::Expr {
+                    kind: ExprKind::Assign(None, _, value),
+                    ..
+                }),
+            ] if matches!(value.kind, ExprKind::Assign(..))
+        ));
+    }
+    #[test]
+    fn test_parse_block_recovers_from_errors() {
+        let (block, diagnostics) = parse("{ foo(1 2); return a; { b c } return; }");
+        let block = block.unwrap();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(matches!(
+            block.stmts.as_slice(),
+            [
+                Stmt::Error(_),
+                Stmt::Return(Some(_), _),
+                Stmt::Block(Block { stmts: inner, .. }),
+                Stmt::Return(None, _),
+            ] if matches!(inner.as_slice(), [Stmt::Error(_)])
+        ));
+    }
+    #[test]
+    fn test_parse_block_unclosed() {
+        let (block, _) = parse("{ foo(a);");
+        let e = block.unwrap_err();
+
+        assert_eq!(e.message, "expected `}`, found end of file");
+        assert_eq!(e.labels[1].message, "this `{` is never closed");
+    }
+}
@@ -0,0 +1,166 @@
+use super::ast::Let;
+use super::expressions::parse_expression;
+use super::statements::expect_semicolon;
+use super::*;
+
+/// Parses a variable declaration:
+///
+/// `var [mut|const]... name (: type)? = expression ;`
+///
+/// `const name ...` is the same as `var const name ...`.
+pub fn parse_variable(p: &mut Parser) -> PResult<Let> {
+    let doc = p.take_docs();
+    let start = p.token.span;
+    let mut modifiers = Vec::new();
+
+    match p.token.kind {
+        TokenKind::Var => {}
+        TokenKind::Const => modifiers.push(Modifiers::Constant),
+        _ => return Err(p.unexpected("`var` or `const`")),
+    }
+
+    p.advance();
+
+    // Modifiers
+    loop {
+        let modifier = match p.token.kind {
+            TokenKind::Mut => Modifiers::Mutable,
+            TokenKind::Const => Modifiers::Constant,
+            _ => break,
+        };
+
+        if modifiers.contains(&modifier) {
+            return Err(
+                Diagnostic::error(format!("{} is specified more than once", p.token.kind))
+                    .with_code(ErrorCode::UnexpectedToken)
+                    .with_label(p.token.span, "remove this"),
+            );
+        }
+
+        modifiers.push(modifier);
+        p.advance();
+    }
+
+    if modifiers.len() > 1 {
+        return Err(
+            Diagnostic::error("a variable can't be both `mut` and `const`")
+                .with_code(ErrorCode::UnexpectedToken)
+                .with_label(p.prev_span(), "remove this")
+                .with_secondary(start, "declared here"),
+        );
+    }
+
+    let name = match p.token.kind.clone() {
+        TokenKind::Unknown(a) => {
+            p.advance();
+            a.to_string()
+        }
+        _ => return Err(p.unexpected("a variable name")),
+    };
+
+    // Type
+    let ty = if p.token.kind == TokenKind::Colon {
+        p.advance();
+
+        let ty = match p.token.kind.clone() {
+            TokenKind::Type(a) => a,
+            TokenKind::Unknown(a) if a == "_" => Type::None,
+            _ => return Err(p.unexpected("a type")),
+        };
+
+        p.advance();
+        ty
+    } else {
+        Type::None
+    };
+
+    if p.token.kind != TokenKind::Eq {
+        return Err(p.unexpected("`=`"));
+    }
+
+    p.advance();
+
+    let value = parse_expression(p)?;
+
+    expect_semicolon(p)?;
+
+    Ok(Let {
+        doc,
+        name,
+        modifiers,
+        ty,
+        value,
+        span: start.to(p.prev_span()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::ExprKind;
+    use crate::span::FileId;
+
+    fn parse(src: &str) -> PResult<Let> {
+        parse_variable(&mut Parser::new(Lexer::new(src, FileId(0))))
+    }
+
+    #[test]
+    fn test_parse_variable() {
+        let var = parse("var foo = 1;").unwrap();
+
+        assert_eq!(var.name, "foo");
+        assert_eq!(var.ty, Type::None);
+        assert!(var.modifiers.is_empty());
+        assert_eq!(var.value.kind, ExprKind::Int(1, Type::None));
+        assert_eq!(var.span, Span::new(FileId(0), 0, 12, 1, 1));
+    }
+    #[test]
+    fn test_parse_variable_with_type() {
+        let i0 = parse("var foo: i32 = 1 + bar;").unwrap();
+        let i1 = parse("var foo: _ = 1;").unwrap();
+        let i2 = parse("var a: u8 = 1;").unwrap();
+        let i3 = parse("var s: string = \"x\";").unwrap();
+        let i4 = parse("var b: u32 = 1;").unwrap();
+
+        assert_eq!(i0.ty, Type::I32);
+        assert!(matches!(i0.value.kind, ExprKind::Binary(..)));
+        assert_eq!(i1.ty, Type::None);
+        assert_eq!(i2.ty, Type::U8);
+        assert_eq!(i3.ty, Type::String);
+        assert_eq!(i3.value.kind, ExprKind::Str("x".to_string()));
+        assert_eq!(i4.ty, Type::U32);
+    }
+    #[test]
+    fn test_parse_variable_with_modifiers() {
+        let mut p = Parser::new(Lexer::new("var mut a = 1;", FileId(0)));
+
+        let var = parse_variable(&mut p).unwrap();
+
+        assert_eq!(var.modifiers, vec![Modifiers::Mutable]);
+        assert_eq!(p.token.kind, TokenKind::Eof);
+    }
+    #[test]
+    fn test_parse_variable_errors() {
+        let messages = [
+            "var = 1;",
+            "var a: 1 = 1;",
+            "var a 1;",
+            "var a = ;",
+            "var a = 1",
+        ]
+        .iter()
+        .map(|code| parse(code).unwrap_err().message)
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "expected a variable name, found `=`",
+                "expected a type, found integer `1`",
+                "expected `=`, found integer `1`",
+                "expected an expression, found `;`",
+                "expected `;`, found end of file",
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Locations in the source code.
+//!
+//! Every token carries a [`Span`], which is then passed on to the parser so errors can point
+//! at the exact place in a `.niff` file.
+
+/// Identifies a file in a [`SourceMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FileId(pub u32);
+
+/// A range of source code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Which file is it in?
+    pub file: FileId,
+    /// Byte offset of the first byte.
+    pub offset: usize,
+    /// Length in bytes.
+    pub len: usize,
+    /// Line of the first character, starting from 1.
+    pub line: u32,
+    /// Column of the first character, starting from 1. Counted in characters, not bytes.
+    pub col: u32,
+}
+
+impl Span {
+    pub fn new(file: FileId, offset: usize, len: usize, line: u32, col: u32) -> Self {
+        Self {
+            file,
+            offset,
+            len,
+            line,
+            col,
+        }
+    }
+    /// Byte offset one past the last byte.
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+    /// Creates a span that covers both `self` and `other`.
+    ///
+    /// `other` is expected to come after `self` in the same file.
+    pub fn to(self, other: Span) -> Span {
+        debug_assert_eq!(self.file, other.file, "Spans from different files.");
+
+        Span {
+            len: other.end().max(self.end()) - self.offset,
+            ..self
+        }
+    }
+}
+
+/// A file that was loaded into the [`SourceMap`].
+#[derive(Debug)]
+pub struct SourceFile {
+    /// The path, or whatever name was given to the file.
+    pub name: String,
+    pub src: String,
+}
+
+/// Stores all the source files, so a [`FileId`] can be turned back into a name and the
+/// source code.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a file and returns the id for it.
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        self.files.push(SourceFile {
+            name: name.into(),
+            src: src.into(),
+        });
+
+        FileId(self.files.len() as u32 - 1)
+    }
+    /// Gets a file by its id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` was not created by this map.
+    pub fn get(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_to() {
+        let a = Span::new(FileId(0), 3, 2, 1, 4);
+        let b = Span::new(FileId(0), 10, 4, 2, 1);
+
+        assert_eq!(a.to(b), Span::new(FileId(0), 3, 11, 1, 4));
+        assert_eq!(a.to(a), a);
+    }
+    #[test]
+    fn test_source_map() {
+        let mut map = SourceMap::new();
+
+        let a = map.add("a.niff", "fn main() {}");
+        let b = map.add("b.niff", "");
+
+        assert_eq!(a, FileId(0));
+        assert_eq!(b, FileId(1));
+        assert_eq!(map.get(a).name, "a.niff");
+        assert_eq!(map.get(a).src, "fn main() {}");
+    }
+}